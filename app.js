@@ -23,6 +23,18 @@ function init() {
     
     // Setup event listeners
     setupEventListeners();
+
+    // Hide layer toggles the backend does not offer; keep them all when it
+    // cannot say (older backend, or not running yet)
+    game.fetchCapabilities().then(caps => {
+        if (!caps) return;
+        const layers = new Set(caps.layers.map(layer => layer.name));
+        for (const mode of Object.keys(game.getHeatMapModesEnabled())) {
+            const id = `heatMap${mode[0].toUpperCase()}${mode.slice(1)}Btn`;
+            const button = document.getElementById(id);
+            if (button) button.style.display = layers.has(mode) ? '' : 'none';
+        }
+    });
     
     // Start animation loop
     requestAnimationFrame(gameLoop);
@@ -48,6 +60,10 @@ function setupEventListeners() {
     const heatMapDifficultyBtn = document.getElementById('heatMapDifficultyBtn');
     const heatMapMarkingDifficultyBtn = document.getElementById('heatMapMarkingDifficultyBtn');
     const heatMapCoverageBtn = document.getElementById('heatMapCoverageBtn');
+    const heatMapCompletionBtn = document.getElementById('heatMapCompletionBtn');
+    const heatMapEpvBtn = document.getElementById('heatMapEpvBtn');
+    const heatMapExpectedGainBtn = document.getElementById('heatMapExpectedGainBtn');
+    const heatMapResetSpaceBtn = document.getElementById('heatMapResetSpaceBtn');
 
     const updateHeatMapButtonStates = () => {
         const enabled = game.getHeatMapModesEnabled();
@@ -59,6 +75,14 @@ function setupEventListeners() {
         heatMapMarkingDifficultyBtn.textContent = enabled.markingDifficulty ? 'Marking On' : 'Marking';
         heatMapCoverageBtn.classList.toggle('active', enabled.coverage);
         heatMapCoverageBtn.textContent = enabled.coverage ? 'Coverage On' : 'Coverage';
+        heatMapCompletionBtn.classList.toggle('active', enabled.completion);
+        heatMapCompletionBtn.textContent = enabled.completion ? 'Completion On' : 'Completion';
+        heatMapEpvBtn.classList.toggle('active', enabled.epv);
+        heatMapEpvBtn.textContent = enabled.epv ? 'EPV On' : 'EPV';
+        heatMapExpectedGainBtn.classList.toggle('active', enabled.expectedGain);
+        heatMapExpectedGainBtn.textContent = enabled.expectedGain ? 'Gain On' : 'Gain';
+        heatMapResetSpaceBtn.classList.toggle('active', enabled.resetSpace);
+        heatMapResetSpaceBtn.textContent = enabled.resetSpace ? 'Reset On' : 'Reset';
         field.setHeatMapVisible(game.isAnyHeatMapEnabled());
     };
 
@@ -78,6 +102,47 @@ function setupEventListeners() {
         game.setHeatMapModeEnabled('coverage', !game.getHeatMapModesEnabled().coverage);
         updateHeatMapButtonStates();
     });
+    heatMapCompletionBtn.addEventListener('click', () => {
+        game.setHeatMapModeEnabled('completion', !game.getHeatMapModesEnabled().completion);
+        updateHeatMapButtonStates();
+    });
+    heatMapEpvBtn.addEventListener('click', () => {
+        game.setHeatMapModeEnabled('epv', !game.getHeatMapModesEnabled().epv);
+        updateHeatMapButtonStates();
+    });
+    heatMapExpectedGainBtn.addEventListener('click', () => {
+        game.setHeatMapModeEnabled('expectedGain', !game.getHeatMapModesEnabled().expectedGain);
+        updateHeatMapButtonStates();
+    });
+    heatMapResetSpaceBtn.addEventListener('click', () => {
+        game.setHeatMapModeEnabled('resetSpace', !game.getHeatMapModesEnabled().resetSpace);
+        updateHeatMapButtonStates();
+    });
+
+    // Stall count: each click counts one, wrapping back to 0 after 10
+    const stallBtn = document.getElementById('stallBtn');
+    stallBtn.addEventListener('click', () => {
+        game.setStall((game.getStall() + 1) % 11);
+        stallBtn.textContent = `Stall ${game.getStall()}`;
+        stallBtn.classList.toggle('active', game.getStall() > 0);
+    });
+
+    // Wind: cycles calm → 15 mph blowing toward each quarter in turn
+    const windSettings = [
+        { label: 'Wind calm',      wind: { speedMph: 0,  directionDegrees: 0 } },
+        { label: 'Wind downwind',  wind: { speedMph: 15, directionDegrees: 0 } },
+        { label: 'Wind cross ↓',   wind: { speedMph: 15, directionDegrees: 90 } },
+        { label: 'Wind upwind',    wind: { speedMph: 15, directionDegrees: 180 } },
+        { label: 'Wind cross ↑',   wind: { speedMph: 15, directionDegrees: 270 } },
+    ];
+    let windIndex = 0;
+    const windBtn = document.getElementById('windBtn');
+    windBtn.addEventListener('click', () => {
+        windIndex = (windIndex + 1) % windSettings.length;
+        game.setWind(windSettings[windIndex].wind);
+        windBtn.textContent = windSettings[windIndex].label;
+        windBtn.classList.toggle('active', windIndex > 0);
+    });
 
     // Normalize toggle: scale heat map values to 0–1 when on
     const normalizeBtn = document.getElementById('normalizeBtn');
@@ -115,6 +180,14 @@ function setupEventListeners() {
         await game.positionOffenderOptimal('2');
     });
 
+    // Animate: 25 ticks of 0.2 s from the current state
+    const animateBtn = document.getElementById('animateBtn');
+    animateBtn.addEventListener('click', async () => {
+        animateBtn.disabled = true;
+        await game.playAnimation(25);
+        animateBtn.disabled = false;
+    });
+
     // Go to stack: offender 1 = 20 yd left, 7 yd down; offender 2 = 20 yd left, 7 yd up
     document.getElementById('goToStackBtn').addEventListener('click', () => {
         game.positionOffenderStack();
@@ -192,6 +265,10 @@ function setupEventListeners() {
                 game.setHeatMapModeEnabled('difficulty', false);
                 game.setHeatMapModeEnabled('markingDifficulty', false);
                 game.setHeatMapModeEnabled('coverage', false);
+                game.setHeatMapModeEnabled('completion', false);
+                game.setHeatMapModeEnabled('epv', false);
+                game.setHeatMapModeEnabled('expectedGain', false);
+                game.setHeatMapModeEnabled('resetSpace', false);
                 updateHeatMapButtonStates();
                 break;
         }
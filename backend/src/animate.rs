@@ -0,0 +1,194 @@
+//! Time-stepped play animation.
+//!
+//! Starting from a game state, every tick:
+//!
+//!   * each cutter without a target (or that reached it last tick) samples a
+//!     new one from `position_offender_optimal`;
+//!   * cutters run toward their targets;
+//!   * each downfield defender works out where `position_defender_optimal`
+//!     would put it against the cutters' new positions and runs toward that.
+//!
+//! Everyone runs at the same constant speed, so a frame never moves a player
+//! more than `speed · tickSeconds`.  The thrower, mark and disc stay put.
+//! The cutters' targets are drawn from one generator seeded with `seed`, so a
+//! seed reproduces the whole timeline.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::cache::LayerCache;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::game::{position_defender_optimal, position_offender_optimal_with_rng};
+use crate::heatmap::{combined_heat_map_sum, validate_grid_size, HeatMapParams};
+use crate::models::{
+    AnimationFrame, AnimationRequest, AnimationResponse, DefenderSearch, GameState,
+};
+use crate::simulate::labels;
+
+// ============================================================================
+// ANIMATION CONSTANTS
+// ============================================================================
+
+/// Upper bound on `ticks` per request.
+pub const ANIMATION_MAX_TICKS: usize = 600;
+
+/// Seconds per tick when the request does not specify `tickSeconds`.
+const ANIMATION_DEFAULT_TICK_SECONDS: f64 = 0.2;
+
+/// Running speed (yards per second) when the request does not specify
+/// `speedYps`; the completion model's player speed.
+const ANIMATION_DEFAULT_SPEED_YPS: f64 = 7.0;
+
+/// Grid size (yards) when the request omits `gridSize`.  Coarse for the same
+/// reason as in simulation: each defender move evaluates many heat maps.
+const ANIMATION_DEFAULT_GRID_SIZE: f64 = 2.0;
+
+/// Validate `req` and generate the timeline on the blocking pool.
+pub async fn run_animation(
+    req: AnimationRequest,
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+) -> Result<AnimationResponse, ApiError> {
+    if req.ticks == 0 || req.ticks > ANIMATION_MAX_TICKS {
+        return Err(ApiError::bad_request(format!(
+            "ticks must be between 1 and {ANIMATION_MAX_TICKS}"
+        )));
+    }
+    let tick_seconds = req.tick_seconds.unwrap_or(ANIMATION_DEFAULT_TICK_SECONDS);
+    let speed = req.speed_yps.unwrap_or(ANIMATION_DEFAULT_SPEED_YPS);
+    let grid_size = req.grid_size.unwrap_or(ANIMATION_DEFAULT_GRID_SIZE);
+    validate_grid_size(grid_size).map_err(|e| ApiError::bad_request(format!("gridSize {e}")))?;
+    for (name, value) in [("tickSeconds", tick_seconds), ("speedYps", speed)] {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(ApiError::bad_request(format!(
+                "{name} must be positive, got {value}"
+            )));
+        }
+    }
+    let seed = req.seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+    let params = config.model.for_field(&req.game_state.field).into_owned();
+    let ticks = req.ticks;
+    let game_state = req.game_state;
+    let frames = tokio::task::spawn_blocking(move || {
+        let mut rng = StdRng::seed_from_u64(seed);
+        animate(
+            game_state,
+            ticks,
+            tick_seconds,
+            speed,
+            grid_size,
+            &params,
+            &cache,
+            &mut rng,
+        )
+    })
+    .await
+    .map_err(|_| ApiError::internal("animation panicked"))?;
+
+    Ok(AnimationResponse {
+        seed,
+        tick_seconds,
+        frames,
+    })
+}
+
+/// The initial state followed by `ticks` frames.
+#[allow(clippy::too_many_arguments)]
+pub fn animate<R: Rng>(
+    mut gs: GameState,
+    ticks: usize,
+    tick_seconds: f64,
+    speed: f64,
+    grid_size: f64,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    rng: &mut R,
+) -> Vec<AnimationFrame> {
+    let step = speed * tick_seconds;
+    let search = DefenderSearch::default();
+    let mut targets: HashMap<String, (f64, f64)> = HashMap::new();
+
+    let mut frames = Vec::with_capacity(ticks + 1);
+    frames.push(frame(&gs, 0, tick_seconds, grid_size, params, cache));
+
+    for tick in 1..=ticks {
+        // Cutters: pick a target when they have none, then run at it.
+        for label in labels(&gs, |p| !p.is_defender && !p.has_disc) {
+            let Some(idx) = player_index(&gs, &label, false) else {
+                continue;
+            };
+            let here = (gs.players[idx].x, gs.players[idx].y);
+            let target = match targets.get(&label) {
+                Some(&t) if t != here => Some(t),
+                _ => {
+                    let mut trial = gs.clone();
+                    let t = position_offender_optimal_with_rng(
+                        &mut trial, grid_size, &label, params, cache, rng,
+                    );
+                    if let Some(t) = t {
+                        targets.insert(label.clone(), t);
+                    }
+                    t
+                }
+            };
+            if let Some(t) = target {
+                (gs.players[idx].x, gs.players[idx].y) = step_toward(here, t, step);
+            }
+        }
+
+        // Defenders: react to where the cutters are now.
+        for label in labels(&gs, |p| p.is_defender && !p.is_mark) {
+            let Some(idx) = player_index(&gs, &label, true) else {
+                continue;
+            };
+            let here = (gs.players[idx].x, gs.players[idx].y);
+            if let Some(t) =
+                position_defender_optimal(&mut gs, grid_size, &label, params, cache, &search)
+            {
+                (gs.players[idx].x, gs.players[idx].y) = step_toward(here, t, step);
+            }
+        }
+
+        frames.push(frame(&gs, tick, tick_seconds, grid_size, params, cache));
+    }
+    frames
+}
+
+fn frame(
+    gs: &GameState,
+    tick: usize,
+    tick_seconds: f64,
+    grid_size: f64,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+) -> AnimationFrame {
+    AnimationFrame {
+        tick,
+        time_seconds: tick as f64 * tick_seconds,
+        game_state: gs.clone(),
+        sum: combined_heat_map_sum(gs, grid_size, params, cache),
+    }
+}
+
+/// Index of the cutter (or downfield defender) with `label`.
+fn player_index(gs: &GameState, label: &str, defender: bool) -> Option<usize> {
+    gs.players.iter().position(|p| {
+        p.is_defender == defender && !p.has_disc && !p.is_mark && p.label.as_deref() == Some(label)
+    })
+}
+
+/// `from` moved at most `max` yards toward `to`, landing exactly on it when
+/// close enough.
+fn step_toward(from: (f64, f64), to: (f64, f64), max: f64) -> (f64, f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let distance = dx.hypot(dy);
+    if distance <= max {
+        return to;
+    }
+    (from.0 + dx * max / distance, from.1 + dy * max / distance)
+}
@@ -1,13 +1,110 @@
 //! Axum route handlers — one function per API endpoint.
 
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use crate::game::{position_defender_optimal, position_offender_optimal, position_offender_stack};
-use crate::heatmap::{calculate_heat_map, combined_heat_map_sum};
+use crate::animate::run_animation;
+use crate::batch::run_batch;
+use crate::cache::LayerCache;
+use crate::capabilities::{capabilities, VERSION};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::export::{export, to_binary, BinaryEncoding, ExportFormat};
+use crate::field::{FieldPreset, FieldPresetInfo};
+use crate::game::{
+    position_defender_hybrid, position_defender_learned, position_defender_optimal,
+    position_offender_optimal_with_rng, position_offender_stack, validate_blend_weight,
+    validate_search, HYBRID_DEFAULT_BLEND_WEIGHT,
+};
+use crate::heatmap::{calculate_heat_map, combined_heat_map_sum, validate_grid_size};
+use crate::knn::{leave_one_out, validate_k, KNN_DEFAULT_K};
 use crate::models::{
-    HeatMapData, HeatMapRequest, HeatMapSumRequest, HeatMapSumResponse, PositionDefenderRequest,
-    PositionOffenderRequest, PositionRequest, PositionResponse,
+    AnimationRequest, AnimationResponse, BatchHeatMapRequest, BatchHeatMapResponse,
+    CapabilitiesResponse, HealthResponse, HeatMapQuery, HeatMapRequest, HeatMapSumRequest,
+    HeatMapSumResponse, LayerCacheStats, PositionDefenderHybridRequest,
+    PositionDefenderLearnedRequest, PositionDefenderRequest, PositionOffenderRequest,
+    PositionRequest, PositionResponse, RecordExampleResponse, RecordedAction, Recording,
+    RecordingSummary, ReplayResponse, Scenario, ScenarioInput, ScenarioQuery, ScenarioSummary,
+    SimulationRequest, SimulationResponse, StartRecordingRequest, TrackingHeatMapRequest,
+    TrackingHeatMapResponse, TrackingImportRequest, TrackingImportResponse, TrainingEvaluation,
+    TrainingEvaluationQuery, TrainingExample, TrainingExampleInput,
 };
+use crate::recording::{replay, RecordingStore};
+use crate::scenarios::ScenarioStore;
+use crate::simulate::run_simulation;
+use crate::telemetry::{record_grid_size, record_state, render_metrics, METRICS_CONTENT_TYPE};
+use crate::tracking::{run_heat_map_sums, run_import};
+use crate::training::TrainingStore;
+
+// ---------------------------------------------------------------------------
+// Shared state
+// ---------------------------------------------------------------------------
+
+/// State shared by every handler.
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub started: Instant,
+    /// Static heat-map layers shared by every request; see `cache`.
+    pub layer_cache: Arc<LayerCache>,
+    pub scenarios: Arc<ScenarioStore>,
+    pub training: Arc<TrainingStore>,
+    pub recordings: Arc<RecordingStore>,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        Self {
+            scenarios: Arc::new(ScenarioStore::new(config.scenario_dir())),
+            training: Arc::new(TrainingStore::open(config.training_dir())),
+            recordings: Arc::new(RecordingStore::new(config.recording_dir())),
+            layer_cache: Arc::new(LayerCache::new(config.layer_cache_capacity)),
+            config: Arc::new(config),
+            started: Instant::now(),
+        }
+    }
+
+    /// The request's grid size, or the configured default when omitted.
+    /// Also noted on the request's log span.
+    pub fn grid_size(&self, requested: Option<f64>) -> Result<f64, ApiError> {
+        let grid_size = requested.unwrap_or(self.config.default_grid_size);
+        validate_grid_size(grid_size)
+            .map_err(|e| ApiError::bad_request(format!("gridSize {e}")))?;
+        record_grid_size(grid_size);
+        Ok(grid_size)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Health and capabilities
+// ---------------------------------------------------------------------------
+
+/// `GET /api/health`
+///
+/// Liveness check: `status` is always `"ok"`, with the version and seconds
+/// since start.
+pub async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        version: VERSION,
+        uptime_seconds: state.started.elapsed().as_secs_f64(),
+    })
+}
+
+/// `GET /api/capabilities`
+///
+/// Layers, combination modes, field presets, export formats, model
+/// parameters, request limits and routes this server supports.
+pub async fn capabilities_handler(State(state): State<AppState>) -> Json<CapabilitiesResponse> {
+    Json(capabilities(&state.config))
+}
 
 // ---------------------------------------------------------------------------
 // Heat-map endpoints
@@ -18,18 +115,166 @@ use crate::models::{
 /// Compute the (optionally normalised) combined heat map from whichever
 /// layers are enabled in `modes`.  Returns `null` when no layers are on or
 /// there is no thrower for the marking layer.
-pub async fn heatmap_handler(Json(req): Json<HeatMapRequest>) -> Json<Option<HeatMapData>> {
-    let data = calculate_heat_map(&req.game_state, &req.modes, req.normalize, req.grid_size);
-    Json(data)
+///
+/// `?format=csv|npy|geojson` returns the grid as a file instead (see
+/// `export`); there is no `null` then, and a missing heat map is a 404.
+/// Without `format`, an `Accept` header naming a binary encoding
+/// (`application/vnd.ufb.heatmap.f32` or `.u8`) gets the compact binary
+/// transport, with `204 No Content` in place of `null`.
+pub async fn heatmap_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HeatMapQuery>,
+    headers: HeaderMap,
+    Json(req): Json<HeatMapRequest>,
+) -> Result<Response, ApiError> {
+    record_state(&req.game_state);
+    let data = calculate_heat_map(
+        &req.game_state,
+        &req.modes,
+        req.normalize,
+        state.grid_size(req.grid_size)?,
+        &state.config.model.for_field(&req.game_state.field),
+        &state.layer_cache,
+    );
+    let format = query.format.unwrap_or_default();
+    if format == ExportFormat::Json {
+        let binary = headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(BinaryEncoding::from_accept);
+        let vary = [(header::VARY, "accept")];
+        return Ok(match (binary, data) {
+            (Some(encoding), Some(data)) => (
+                vary,
+                [(header::CONTENT_TYPE, encoding.media_type())],
+                to_binary(&data, encoding),
+            )
+                .into_response(),
+            (Some(_), None) => (vary, StatusCode::NO_CONTENT).into_response(),
+            (None, data) => (vary, Json(data)).into_response(),
+        });
+    }
+    let data = data.ok_or_else(|| {
+        ApiError::not_found("no heat map: no layers are enabled or there is no thrower")
+    })?;
+    let headers = [
+        (header::CONTENT_TYPE, format.content_type().to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"heatmap.{}\"", format.extension()),
+        ),
+    ];
+    Ok((headers, export(&data, format)).into_response())
 }
 
 /// `POST /api/heatmap-sum`
 ///
 /// Return the scalar sum of all cells in the pre-normalised, product-combined
 /// heat map (all 4 layers active).  Returns `null` when there is no thrower.
-pub async fn heatmap_sum_handler(Json(req): Json<HeatMapSumRequest>) -> Json<HeatMapSumResponse> {
-    let sum = combined_heat_map_sum(&req.game_state, req.grid_size);
-    Json(HeatMapSumResponse { sum })
+pub async fn heatmap_sum_handler(
+    State(state): State<AppState>,
+    Json(req): Json<HeatMapSumRequest>,
+) -> Result<Json<HeatMapSumResponse>, ApiError> {
+    record_state(&req.game_state);
+    let grid_size = state.grid_size(req.grid_size)?;
+    let sum = combined_heat_map_sum(
+        &req.game_state,
+        grid_size,
+        &state.config.model.for_field(&req.game_state.field),
+        &state.layer_cache,
+    );
+    state
+        .recordings
+        .record(grid_size, RecordedAction::HeatMapSum, req.game_state, sum)
+        .await;
+    Ok(Json(HeatMapSumResponse { sum }))
+}
+
+/// `POST /api/heatmap-batch`
+///
+/// Evaluate many game states at once.  Entries may omit `field` / `gridSize`
+/// when the request supplies shared values.  Sums are returned by default;
+/// heat maps only when `modes` is given.  Failed entries carry an `error`
+/// and do not affect the others.
+pub async fn heatmap_batch_handler(
+    State(state): State<AppState>,
+    Json(req): Json<BatchHeatMapRequest>,
+) -> Result<Json<BatchHeatMapResponse>, ApiError> {
+    Ok(Json(
+        run_batch(req, state.config.clone(), state.layer_cache.clone()).await?,
+    ))
+}
+
+/// `POST /api/simulate`
+///
+/// Play the point out `simulations` times from the given state and report
+/// the score rate, average passes and the most common opening throw
+/// sequences.  Pass `seed` to reproduce a run.
+pub async fn simulate_handler(
+    State(state): State<AppState>,
+    Json(req): Json<SimulationRequest>,
+) -> Result<Json<SimulationResponse>, ApiError> {
+    Ok(Json(run_simulation(req, state.config.clone()).await?))
+}
+
+/// `POST /api/animate`
+///
+/// Generate `ticks` time steps from the given state: cutters run toward
+/// heat-map-sampled targets and defenders chase their optimal positions, at
+/// constant speed.  Returns every frame's game state and heat-map sum.  Pass
+/// `seed` to reproduce a timeline.
+pub async fn animate_handler(
+    State(state): State<AppState>,
+    Json(req): Json<AnimationRequest>,
+) -> Result<Json<AnimationResponse>, ApiError> {
+    Ok(Json(
+        run_animation(req, state.config.clone(), state.layer_cache.clone()).await?,
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Tracking data endpoints
+// ---------------------------------------------------------------------------
+
+/// `POST /api/tracking/import`
+///
+/// Convert per-frame tracking CSV (`csv`, plus coordinate options) into one
+/// game state per frame, with teams, disc holder, mark and labels filled in.
+pub async fn tracking_import_handler(
+    Json(req): Json<TrackingImportRequest>,
+) -> Result<Json<TrackingImportResponse>, ApiError> {
+    Ok(Json(run_import(req).await?))
+}
+
+/// `POST /api/tracking/heatmap-sums`
+///
+/// Import tracking CSV as for `tracking/import` and return the combined
+/// heat-map sum of every frame (`null` while the disc is in the air).
+pub async fn tracking_heatmap_sums_handler(
+    State(state): State<AppState>,
+    Json(req): Json<TrackingHeatMapRequest>,
+) -> Result<Json<TrackingHeatMapResponse>, ApiError> {
+    Ok(Json(
+        run_heat_map_sums(req, state.config.clone(), state.layer_cache.clone()).await?,
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Field presets
+// ---------------------------------------------------------------------------
+
+/// `GET /api/field-presets`
+///
+/// Named fields a request may send as `field`, in their own units and in
+/// yards.  Player and disc coordinates on every field are in yards
+/// (`coordinateUnits`).
+pub async fn field_presets_handler() -> Json<Vec<FieldPresetInfo>> {
+    Json(
+        FieldPreset::ALL
+            .into_iter()
+            .map(FieldPresetInfo::from)
+            .collect(),
+    )
 }
 
 // ---------------------------------------------------------------------------
@@ -39,15 +284,46 @@ pub async fn heatmap_sum_handler(Json(req): Json<HeatMapSumRequest>) -> Json<Hea
 /// `POST /api/position-defender`
 ///
 /// Body must include `defenderLabel` (e.g. "1", "2").  Moves that defender to
-/// the cell within 5 yards of the offender with the same label that minimises
-/// the combined heat-map sum (other defenders' coverage is included).
-/// Returns `null` when no matching defender or offender exists.
+/// the cell in the `search` region (default: within 5 yards) around the
+/// offender with the same label that minimises the combined heat-map sum
+/// (other defenders' coverage is included); `search.refine` refines it off
+/// the grid.  Returns `null` when no matching defender or offender exists.
 pub async fn position_defender_handler(
+    State(state): State<AppState>,
     Json(req): Json<PositionDefenderRequest>,
-) -> Json<Option<PositionResponse>> {
+) -> Result<Json<Option<PositionResponse>>, ApiError> {
+    validate_search(&req.search).map_err(ApiError::bad_request)?;
+    let recorded = state
+        .recordings
+        .is_recording()
+        .await
+        .then(|| req.game_state.clone());
+    record_state(&req.game_state);
     let mut gs = req.game_state;
-    let result = position_defender_optimal(&mut gs, req.grid_size, &req.defender_label);
-    Json(result.map(|(x, y)| PositionResponse { x, y }))
+    let params = state.config.model.for_field(&gs.field).into_owned();
+    let grid_size = state.grid_size(req.grid_size)?;
+    let result = position_defender_optimal(
+        &mut gs,
+        grid_size,
+        &req.defender_label,
+        &params,
+        &state.layer_cache,
+        &req.search,
+    )
+    .map(|(x, y)| PositionResponse { x, y });
+    if let Some(recorded) = recorded {
+        let sum = combined_heat_map_sum(&gs, grid_size, &params, &state.layer_cache);
+        let action = RecordedAction::PositionDefender {
+            defender_label: req.defender_label,
+            search: req.search,
+            result: result.clone(),
+        };
+        state
+            .recordings
+            .record(grid_size, action, recorded, sum)
+            .await;
+    }
+    Ok(Json(result))
 }
 
 /// `POST /api/position-offender`
@@ -56,21 +332,396 @@ pub async fn position_defender_handler(
 /// a cell sampled from the combined heat map.  Returns `null` when no thrower
 /// or matching offender is present.
 pub async fn position_offender_handler(
+    State(state): State<AppState>,
     Json(req): Json<PositionOffenderRequest>,
-) -> Json<Option<PositionResponse>> {
+) -> Result<Json<Option<PositionResponse>>, ApiError> {
+    let recorded = state
+        .recordings
+        .is_recording()
+        .await
+        .then(|| req.game_state.clone());
+    record_state(&req.game_state);
+    let mut gs = req.game_state;
+    let params = state.config.model.for_field(&gs.field).into_owned();
+    let grid_size = state.grid_size(req.grid_size)?;
+    // Seeded explicitly so a recording can replay the same sample.
+    let seed = rand::thread_rng().gen();
+    let result = position_offender_optimal_with_rng(
+        &mut gs,
+        grid_size,
+        &req.offender_label,
+        &params,
+        &state.layer_cache,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .map(|(x, y)| PositionResponse { x, y });
+    if let Some(recorded) = recorded {
+        let sum = combined_heat_map_sum(&gs, grid_size, &params, &state.layer_cache);
+        let action = RecordedAction::PositionOffender {
+            offender_label: req.offender_label,
+            seed,
+            result: result.clone(),
+        };
+        state
+            .recordings
+            .record(grid_size, action, recorded, sum)
+            .await;
+    }
+    Ok(Json(result))
+}
+
+/// `POST /api/position-defender-learned`
+///
+/// Body must include `defenderLabel`.  Moves that defender to the position
+/// predicted by the k-NN model trained on the recorded examples (optional
+/// `k`, default 5).  Returns `null` when no matching pair exists or fewer
+/// than 3 training samples are stored, 400 for a `k` outside 1 to the number
+/// of training samples.
+pub async fn position_defender_learned_handler(
+    State(state): State<AppState>,
+    Json(req): Json<PositionDefenderLearnedRequest>,
+) -> Result<Json<Option<PositionResponse>>, ApiError> {
+    let recorded = state
+        .recordings
+        .is_recording()
+        .await
+        .then(|| req.game_state.clone());
+    let (samples, _) = state.training.samples().await;
+    if let Some(k) = req.k {
+        validate_k(k, samples.len()).map_err(ApiError::bad_request)?;
+    }
+    record_state(&req.game_state);
+    let mut gs = req.game_state;
+    let k = req.k.unwrap_or(KNN_DEFAULT_K);
+    let result = position_defender_learned(&mut gs, &req.defender_label, &samples, k)
+        .map(|(x, y)| PositionResponse { x, y });
+    if let Some(recorded) = recorded {
+        let grid_size = state.grid_size(None)?;
+        let params = state.config.model.for_field(&gs.field);
+        let sum = combined_heat_map_sum(&gs, grid_size, &params, &state.layer_cache);
+        let action = RecordedAction::PositionDefenderLearned {
+            defender_label: req.defender_label,
+            k,
+            result: result.clone(),
+        };
+        state
+            .recordings
+            .record(grid_size, action, recorded, sum)
+            .await;
+    }
+    Ok(Json(result))
+}
+
+/// `POST /api/position-defender-hybrid`
+///
+/// Body must include `defenderLabel`.  Picks the cell in the `search` region
+/// (as for `position-defender`) that minimises a blend of the normalised
+/// heat-map sum and the distance from the learned (k-NN) prediction,
+/// weighted by `blendWeight` (0–1, default 0.5).  Falls back to the heat-map
+/// optimum when there are too few training samples.  Returns `null` when no
+/// matching pair exists, 400 for a `blendWeight` outside [0, 1] or a `k`
+/// outside 1 to the number of training samples.
+pub async fn position_defender_hybrid_handler(
+    State(state): State<AppState>,
+    Json(req): Json<PositionDefenderHybridRequest>,
+) -> Result<Json<Option<PositionResponse>>, ApiError> {
+    validate_search(&req.search).map_err(ApiError::bad_request)?;
+    if let Some(w) = req.blend_weight {
+        validate_blend_weight(w).map_err(ApiError::bad_request)?;
+    }
+    let recorded = state
+        .recordings
+        .is_recording()
+        .await
+        .then(|| req.game_state.clone());
+    let (samples, _) = state.training.samples().await;
+    if let Some(k) = req.k {
+        validate_k(k, samples.len()).map_err(ApiError::bad_request)?;
+    }
+    record_state(&req.game_state);
     let mut gs = req.game_state;
-    let result = position_offender_optimal(&mut gs, req.grid_size, &req.offender_label);
-    Json(result.map(|(x, y)| PositionResponse { x, y }))
+    let params = state.config.model.for_field(&gs.field).into_owned();
+    let grid_size = state.grid_size(req.grid_size)?;
+    let k = req.k.unwrap_or(KNN_DEFAULT_K);
+    let blend_weight = req.blend_weight.unwrap_or(HYBRID_DEFAULT_BLEND_WEIGHT);
+    let result = position_defender_hybrid(
+        &mut gs,
+        grid_size,
+        &req.defender_label,
+        &params,
+        &state.layer_cache,
+        &req.search,
+        &samples,
+        k,
+        blend_weight,
+    )
+    .map(|(x, y)| PositionResponse { x, y });
+    if let Some(recorded) = recorded {
+        let sum = combined_heat_map_sum(&gs, grid_size, &params, &state.layer_cache);
+        let action = RecordedAction::PositionDefenderHybrid {
+            defender_label: req.defender_label,
+            search: req.search,
+            k,
+            blend_weight,
+            result: result.clone(),
+        };
+        state
+            .recordings
+            .record(grid_size, action, recorded, sum)
+            .await;
+    }
+    Ok(Json(result))
 }
 
 /// `POST /api/position-stack`
 ///
-/// Return the stack position (centre of field, 20 yards downfield from disc).
+/// Return the stack position (centre of field, 20 yards downfield from disc on
+/// the reference field, scaled with the field's length).
 /// Returns `null` when no offender (non-disc, non-defender) is found.
 pub async fn position_stack_handler(
+    State(state): State<AppState>,
     Json(req): Json<PositionRequest>,
-) -> Json<Option<PositionResponse>> {
+) -> Result<Json<Option<PositionResponse>>, ApiError> {
+    let recorded = state
+        .recordings
+        .is_recording()
+        .await
+        .then(|| req.game_state.clone());
+    record_state(&req.game_state);
     let mut gs = req.game_state;
-    let result = position_offender_stack(&mut gs);
-    Json(result.map(|(x, y)| PositionResponse { x, y }))
+    let result = position_offender_stack(&mut gs).map(|(x, y)| PositionResponse { x, y });
+    if let Some(recorded) = recorded {
+        let grid_size = state.grid_size(req.grid_size)?;
+        let params = state.config.model.for_field(&gs.field);
+        let sum = combined_heat_map_sum(&gs, grid_size, &params, &state.layer_cache);
+        let action = RecordedAction::PositionStack {
+            result: result.clone(),
+        };
+        state
+            .recordings
+            .record(grid_size, action, recorded, sum)
+            .await;
+    }
+    Ok(Json(result))
+}
+
+// ---------------------------------------------------------------------------
+// Scenario library endpoints
+// ---------------------------------------------------------------------------
+
+/// `GET /api/scenarios?tag=a,b&q=text`
+///
+/// List stored scenarios (without their game states), optionally filtered to
+/// those carrying every given tag and / or containing `q` in name or notes.
+pub async fn list_scenarios_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ScenarioQuery>,
+) -> Result<Json<Vec<ScenarioSummary>>, ApiError> {
+    Ok(Json(state.scenarios.list(&query).await?))
+}
+
+/// `POST /api/scenarios`
+///
+/// Store a new scenario; its id is derived from the name.
+pub async fn create_scenario_handler(
+    State(state): State<AppState>,
+    Json(input): Json<ScenarioInput>,
+) -> Result<(StatusCode, Json<Scenario>), ApiError> {
+    let scenario = state.scenarios.create(input).await?;
+    Ok((StatusCode::CREATED, Json(scenario)))
+}
+
+/// `GET /api/scenarios/:id`
+pub async fn get_scenario_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Scenario>, ApiError> {
+    Ok(Json(state.scenarios.get(&id).await?))
+}
+
+/// `PUT /api/scenarios/:id`
+///
+/// Replace a scenario's metadata and game state.
+pub async fn update_scenario_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<ScenarioInput>,
+) -> Result<Json<Scenario>, ApiError> {
+    Ok(Json(state.scenarios.update(&id, input).await?))
+}
+
+/// `DELETE /api/scenarios/:id`
+pub async fn delete_scenario_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state.scenarios.delete(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ---------------------------------------------------------------------------
+// Training data endpoints
+// ---------------------------------------------------------------------------
+
+/// `POST /api/training/examples`
+///
+/// Record a game state as a labelled example: every non-mark defender whose
+/// label matches a downfield offender becomes one training sample.
+pub async fn record_example_handler(
+    State(state): State<AppState>,
+    Json(input): Json<TrainingExampleInput>,
+) -> Result<(StatusCode, Json<RecordExampleResponse>), ApiError> {
+    let (example, samples, total_samples) = state.training.record(input).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(RecordExampleResponse {
+            example,
+            samples,
+            total_samples,
+        }),
+    ))
+}
+
+/// `GET /api/training/examples`
+pub async fn list_examples_handler(State(state): State<AppState>) -> Json<Vec<TrainingExample>> {
+    Json(state.training.examples().await)
+}
+
+/// `DELETE /api/training/examples` — remove every example.
+pub async fn clear_examples_handler(State(state): State<AppState>) -> Result<StatusCode, ApiError> {
+    state.training.delete(None).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /api/training/examples/:id`
+pub async fn delete_example_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    state.training.delete(Some(id)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `GET /api/training/evaluate?k=5`
+///
+/// Leave-one-out error of the learned defender model: each sample is
+/// predicted from all the others and compared with where the coach put it.
+/// 400 for a `k` outside 1 to the number of training samples.
+pub async fn evaluate_training_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TrainingEvaluationQuery>,
+) -> Result<Json<TrainingEvaluation>, ApiError> {
+    let (samples, examples) = state.training.samples().await;
+    if let Some(k) = query.k {
+        validate_k(k, samples.len()).map_err(ApiError::bad_request)?;
+    }
+    let k = query.k.unwrap_or(KNN_DEFAULT_K);
+    let count = samples.len();
+    // Quadratic in the number of samples; keep it off the async workers.
+    let loo = tokio::task::spawn_blocking(move || leave_one_out(&samples, k))
+        .await
+        .map_err(|_| ApiError::internal("evaluation panicked"))?;
+    Ok(Json(TrainingEvaluation {
+        k,
+        examples,
+        samples: count,
+        mean_error_yards: loo.as_ref().map(|e| e.mean_yards),
+        median_error_yards: loo.as_ref().map(|e| e.median_yards),
+        max_error_yards: loo.as_ref().map(|e| e.max_yards),
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// Recording endpoints
+// ---------------------------------------------------------------------------
+
+/// `POST /api/recordings/start`
+///
+/// Start recording every heat-map sum, positioning call and session move,
+/// with the state it applied to and the resulting sum.  Optional `name`.
+/// Fails when a recording is already running.
+pub async fn start_recording_handler(
+    State(state): State<AppState>,
+    body: Option<Json<StartRecordingRequest>>,
+) -> Result<(StatusCode, Json<RecordingSummary>), ApiError> {
+    let Json(req) = body.unwrap_or_default();
+    let summary = state.recordings.start(req.name).await?;
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+/// `POST /api/recordings/stop`
+pub async fn stop_recording_handler(
+    State(state): State<AppState>,
+) -> Result<Json<RecordingSummary>, ApiError> {
+    Ok(Json(state.recordings.stop().await?))
+}
+
+/// `GET /api/recordings`
+///
+/// Every stored recording (without its events), newest first.
+pub async fn list_recordings_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RecordingSummary>>, ApiError> {
+    Ok(Json(state.recordings.list().await?))
+}
+
+/// `GET /api/recordings/:id`
+pub async fn get_recording_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Recording>, ApiError> {
+    Ok(Json(state.recordings.get(&id).await?))
+}
+
+/// `POST /api/recordings/:id/replay`
+///
+/// Re-run every recorded event against the current model and report its
+/// recorded and new heat-map sum and positioning result.
+pub async fn replay_recording_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ReplayResponse>, ApiError> {
+    let recording = state.recordings.get(&id).await?;
+    let (samples, _) = state.training.samples().await;
+    let config = state.config.clone();
+    let cache = state.layer_cache.clone();
+    let response =
+        tokio::task::spawn_blocking(move || replay(&recording, &config.model, &samples, &cache))
+            .await
+            .map_err(|_| ApiError::internal("replay panicked"))?;
+    Ok(Json(response))
+}
+
+// ---------------------------------------------------------------------------
+// Layer cache
+// ---------------------------------------------------------------------------
+
+/// `GET /api/cache`
+///
+/// Size and hit / miss counts of the heat-map layer cache, per layer.
+pub async fn cache_stats_handler(State(state): State<AppState>) -> Json<LayerCacheStats> {
+    Json(state.layer_cache.stats())
+}
+
+/// `DELETE /api/cache`
+///
+/// Drop every cached layer (the counters are kept).
+pub async fn clear_cache_handler(State(state): State<AppState>) -> StatusCode {
+    state.layer_cache.clear();
+    StatusCode::NO_CONTENT
+}
+
+// ---------------------------------------------------------------------------
+// Metrics
+// ---------------------------------------------------------------------------
+
+/// `GET /metrics`
+///
+/// Request counts, latency histograms and layer-cache statistics in the
+/// Prometheus text format.
+pub async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)],
+        render_metrics(&state.layer_cache),
+    )
 }
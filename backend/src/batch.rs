@@ -0,0 +1,135 @@
+//! Concurrent evaluation of many game states in one request.
+//!
+//! Each entry is resolved against the request's shared field / grid size
+//! (falling back to the server's default grid size) and evaluated on the
+//! blocking thread pool, at most one entry per CPU at a time so a large batch
+//! cannot starve other requests of blocking threads.  A bad entry (missing field,
+//! invalid grid size, panic during evaluation) is reported in its own result
+//! and never fails the rest of the batch.
+
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+use crate::cache::LayerCache;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::heatmap::{calculate_heat_map, combined_heat_map_sum, validate_grid_size};
+use crate::models::{
+    BatchGameState, BatchHeatMapRequest, BatchHeatMapResponse, BatchHeatMapResult, FieldDimensions,
+    GameState,
+};
+
+/// Upper bound on `gameStates` per `POST /api/heatmap-batch` request.
+pub const BATCH_MAX_ENTRIES: usize = 1_000;
+
+/// Check the request's size, then evaluate it.
+pub async fn run_batch(
+    req: BatchHeatMapRequest,
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+) -> Result<BatchHeatMapResponse, ApiError> {
+    if req.game_states.len() > BATCH_MAX_ENTRIES {
+        return Err(ApiError::bad_request(format!(
+            "gameStates may hold at most {BATCH_MAX_ENTRIES} entries, got {}",
+            req.game_states.len()
+        )));
+    }
+    Ok(evaluate_batch(req, config, cache).await)
+}
+
+/// Evaluate every entry of `req` concurrently.  Results are returned in
+/// request order.
+pub async fn evaluate_batch(
+    req: BatchHeatMapRequest,
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+) -> BatchHeatMapResponse {
+    let BatchHeatMapRequest {
+        game_states,
+        field,
+        grid_size,
+        include_sum,
+        modes,
+        normalize,
+    } = req;
+
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let permits = Arc::new(Semaphore::new(workers));
+    let mut tasks = Vec::with_capacity(game_states.len());
+    for entry in game_states {
+        // The semaphore is never closed, so acquiring only waits.
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        let field = field.clone();
+        let modes = modes.clone();
+        let config = config.clone();
+        let cache = cache.clone();
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let shared_grid_size = grid_size.unwrap_or(config.default_grid_size);
+            let (gs, grid_size) = resolve_entry(entry, field, shared_grid_size)?;
+            let params = &config.model.for_field(&gs.field);
+            let sum = if include_sum {
+                combined_heat_map_sum(&gs, grid_size, params, &cache)
+            } else {
+                None
+            };
+            let heat_map = modes
+                .as_ref()
+                .and_then(|m| calculate_heat_map(&gs, m, normalize, grid_size, params, &cache));
+            Ok::<_, String>((sum, heat_map))
+        }));
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (index, task) in tasks.into_iter().enumerate() {
+        let outcome = task
+            .await
+            .unwrap_or_else(|_| Err("evaluation panicked".to_string()));
+        results.push(match outcome {
+            Ok((sum, heat_map)) => BatchHeatMapResult {
+                index,
+                sum,
+                heat_map,
+                error: None,
+            },
+            Err(error) => BatchHeatMapResult {
+                index,
+                sum: None,
+                heat_map: None,
+                error: Some(error),
+            },
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    BatchHeatMapResponse {
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    }
+}
+
+/// Fill in the shared field / grid size and validate the entry.
+fn resolve_entry(
+    entry: BatchGameState,
+    shared_field: Option<FieldDimensions>,
+    shared_grid_size: f64,
+) -> Result<(GameState, f64), String> {
+    let field = entry
+        .field
+        .or(shared_field)
+        .ok_or("missing `field` (none on the entry and no shared field)")?;
+    let grid_size = entry.grid_size.unwrap_or(shared_grid_size);
+    validate_grid_size(grid_size).map_err(|e| format!("gridSize {e}"))?;
+    let gs = GameState {
+        players: entry.players,
+        disc: entry.disc,
+        field,
+        stall: entry.stall,
+        wind: entry.wind,
+    };
+    Ok((gs, grid_size))
+}
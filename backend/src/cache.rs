@@ -0,0 +1,444 @@
+//! Bounded LRU cache of the static heat-map layers.
+//!
+//! The catch, difficulty and marking layers depend only on the disc, the
+//! thrower, the field, the grid, the stall count, the wind and the model
+//! parameters — not on where the other players stand.  Positioning searches
+//! evaluate hundreds of states that differ only in one defender or receiver,
+//! and the UI re-sends unchanged states, so these layers are computed once
+//! and shared.  The coverage layer and the optional layers are cheap or
+//! player-dependent and are always recomputed.
+//!
+//! Entries are keyed by exactly the inputs a layer reads, encoded as bytes:
+//! floats by their bit pattern, parameter blocks by their JSON encoding.  The
+//! server keeps one cache in `AppState`, sized by `layer_cache_capacity`
+//! (0 disables it); `eval` builds its own, and calibration, whose trial
+//! parameters never repeat, uses `LayerCache::disabled()`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::heatmap::{
+    get_catch_layer_for_state, get_difficulty_layer_for_state, get_marking_difficulty_layer,
+    stall_pressure, HeatMapParams, MarkParams, SIDELINE_X_MIN,
+};
+use crate::models::{Disc, GameState, LayerCacheStats, LayerCacheStatsEntry, Player};
+
+/// Entries kept when the configuration does not say otherwise.  A layer at
+/// the default 1-yard grid is ~35 kB, so this is a few MB at most.
+pub const DEFAULT_LAYER_CACHE_CAPACITY: usize = 128;
+
+pub type Grid = Vec<Vec<f64>>;
+
+/// A layer's inputs, byte for byte.  Shared between the map and the recency
+/// index.
+type Key = Arc<[u8]>;
+
+/// The cached layers, in the order they are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedLayer {
+    Catch,
+    Difficulty,
+    MarkingDifficulty,
+}
+
+impl CachedLayer {
+    pub const ALL: [CachedLayer; 3] = [
+        CachedLayer::Catch,
+        CachedLayer::Difficulty,
+        CachedLayer::MarkingDifficulty,
+    ];
+
+    /// The layer's name in `HeatMapModes` / `HeatMapData::mode`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CachedLayer::Catch => "catch",
+            CachedLayer::Difficulty => "difficulty",
+            CachedLayer::MarkingDifficulty => "markingDifficulty",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct Entry {
+    value: Arc<Grid>,
+    /// Position in `Lru::recency`.
+    tick: u64,
+}
+
+/// Hash map plus a recency index: the smallest tick is the least recently
+/// used entry.
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<Key, Entry>,
+    recency: BTreeMap<u64, Key>,
+    next_tick: u64,
+    evictions: u64,
+}
+
+impl Lru {
+    fn get(&mut self, key: &[u8]) -> Option<Arc<Grid>> {
+        let tick = self.next_tick;
+        let entry = self.entries.get_mut(key)?;
+        if let Some(key) = self.recency.remove(&entry.tick) {
+            self.recency.insert(tick, key);
+        }
+        entry.tick = tick;
+        self.next_tick += 1;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: Key, value: Arc<Grid>, capacity: usize) {
+        if let Some(old) = self.entries.remove(&key) {
+            self.recency.remove(&old.tick);
+        }
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            self.evictions += 1;
+        }
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, Entry { value, tick });
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+pub struct LayerCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+    counters: [Counters; 3],
+}
+
+impl std::fmt::Debug for LayerCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayerCache")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for LayerCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_LAYER_CACHE_CAPACITY)
+    }
+}
+
+impl LayerCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+            counters: Default::default(),
+        }
+    }
+
+    /// A cache that stores nothing, for callers whose inputs never repeat.
+    pub fn disabled() -> Self {
+        Self::new(0)
+    }
+
+    /// The cached value for `key`, computing and storing it on a miss.  The
+    /// lock is not held while computing, so two threads missing on the same
+    /// key may both compute it; the results are identical.  A key that could
+    /// not be built is computed without caching.
+    fn get_or_compute(
+        &self,
+        layer: CachedLayer,
+        key: Result<Key, serde_json::Error>,
+        compute: impl FnOnce() -> Grid,
+    ) -> Arc<Grid> {
+        let counters = &self.counters[layer.index()];
+        let key = match key {
+            Ok(key) if self.capacity > 0 => key,
+            Ok(_) => {
+                counters.misses.fetch_add(1, Ordering::Relaxed);
+                return Arc::new(compute());
+            }
+            Err(e) => {
+                tracing::warn!(layer = layer.name(), "cannot build layer cache key: {e}");
+                counters.misses.fetch_add(1, Ordering::Relaxed);
+                return Arc::new(compute());
+            }
+        };
+        if let Some(value) = self.lock().get(&key) {
+            counters.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        counters.misses.fetch_add(1, Ordering::Relaxed);
+        let value = Arc::new(compute());
+        self.lock().insert(key, value.clone(), self.capacity);
+        value
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        // in a way that matters for a cache; keep serving.
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stats(&self) -> LayerCacheStats {
+        let (entries, evictions) = {
+            let lru = self.lock();
+            (lru.entries.len(), lru.evictions)
+        };
+        let layers: Vec<LayerCacheStatsEntry> = CachedLayer::ALL
+            .iter()
+            .map(|&layer| {
+                let c = &self.counters[layer.index()];
+                LayerCacheStatsEntry {
+                    layer: layer.name().to_string(),
+                    hits: c.hits.load(Ordering::Relaxed),
+                    misses: c.misses.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        let hits: u64 = layers.iter().map(|l| l.hits).sum();
+        let misses: u64 = layers.iter().map(|l| l.misses).sum();
+        LayerCacheStats {
+            capacity: self.capacity,
+            entries,
+            evictions,
+            hits,
+            misses,
+            hit_rate: if hits + misses > 0 {
+                hits as f64 / (hits + misses) as f64
+            } else {
+                0.0
+            },
+            layers,
+        }
+    }
+
+    /// Drop every entry.  Counters are kept.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Keys
+// ---------------------------------------------------------------------------
+
+/// A layer's inputs, appended as bytes.  The first serialization error is
+/// kept and reported by `finish`.
+struct Fingerprint(Result<Vec<u8>, serde_json::Error>);
+
+impl Fingerprint {
+    fn new(layer: CachedLayer, num_cells_x: usize, num_cells_y: usize, grid_size: f64) -> Self {
+        let mut bytes = vec![layer.index() as u8];
+        bytes.extend_from_slice(&(num_cells_x as u64).to_le_bytes());
+        bytes.extend_from_slice(&(num_cells_y as u64).to_le_bytes());
+        Fingerprint(Ok(bytes)).f64(grid_size)
+    }
+
+    fn f64(mut self, value: f64) -> Self {
+        // Fold −0 into +0 so the two share an entry, as they compare alike.
+        let value = if value == 0.0 { 0.0 } else { value };
+        if let Ok(bytes) = &mut self.0 {
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        self
+    }
+
+    fn value<T: Serialize>(mut self, value: &T) -> Self {
+        if let Ok(bytes) = &mut self.0 {
+            match serde_json::to_vec(value) {
+                // Length-prefixed so adjacent blocks cannot run together.
+                Ok(json) => {
+                    bytes.extend_from_slice(&(json.len() as u64).to_le_bytes());
+                    bytes.extend_from_slice(&json);
+                }
+                Err(e) => self.0 = Err(e),
+            }
+        }
+        self
+    }
+
+    fn disc(self, disc: &Disc) -> Self {
+        self.f64(disc.x).f64(disc.y)
+    }
+
+    /// Wind and stall as the `_for_state` builders see them: calm air and
+    /// no stall pressure skip their parameter blocks entirely.
+    fn state_adjustments(mut self, game_state: &GameState, params: &HeatMapParams) -> Self {
+        if !game_state.wind.is_calm() {
+            self = self
+                .f64(game_state.wind.speed_mph)
+                .f64(game_state.wind.direction_degrees)
+                .value(&params.wind);
+        }
+        let pressure = stall_pressure(game_state.stall, &params.stall);
+        self = self.f64(pressure);
+        if pressure > 0.0 {
+            self = self.value(&params.stall);
+        }
+        self
+    }
+
+    fn finish(self) -> Result<Key, serde_json::Error> {
+        self.0.map(Key::from)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Cached layer builders
+// ---------------------------------------------------------------------------
+
+impl LayerCache {
+    /// `get_catch_layer_for_state`, cached.
+    pub fn catch_layer(
+        &self,
+        num_cells_x: usize,
+        num_cells_y: usize,
+        grid_size: f64,
+        game_state: &GameState,
+        params: &HeatMapParams,
+    ) -> Arc<Grid> {
+        let mut key = Fingerprint::new(CachedLayer::Catch, num_cells_x, num_cells_y, grid_size)
+            .disc(&game_state.disc)
+            .value(&game_state.field)
+            .value(&params.catch)
+            .state_adjustments(game_state, params);
+        if stall_pressure(game_state.stall, &params.stall) > 0.0 {
+            key = key.value(&params.reset);
+        }
+        self.get_or_compute(CachedLayer::Catch, key.finish(), || {
+            get_catch_layer_for_state(num_cells_x, num_cells_y, grid_size, game_state, params)
+        })
+    }
+
+    /// `get_difficulty_layer_for_state`, cached.
+    pub fn difficulty_layer(
+        &self,
+        num_cells_x: usize,
+        num_cells_y: usize,
+        grid_size: f64,
+        game_state: &GameState,
+        params: &HeatMapParams,
+    ) -> Arc<Grid> {
+        let key = Fingerprint::new(CachedLayer::Difficulty, num_cells_x, num_cells_y, grid_size)
+            .disc(&game_state.disc)
+            .value(&params.difficulty)
+            .state_adjustments(game_state, params)
+            .finish();
+        self.get_or_compute(CachedLayer::Difficulty, key, || {
+            get_difficulty_layer_for_state(num_cells_x, num_cells_y, grid_size, game_state, params)
+        })
+    }
+
+    /// `get_marking_difficulty_layer`, cached.  Keyed by the thrower's position
+    /// rather than the whole player list; `None` (no thrower on the field) is
+    /// not cached.
+    pub fn marking_layer(
+        &self,
+        num_cells_x: usize,
+        num_cells_y: usize,
+        grid_size: f64,
+        players: &[Player],
+        disc: &Disc,
+        params: &MarkParams,
+    ) -> Option<(Arc<Grid>, f64, f64)> {
+        let thrower = players.iter().find(|p| p.has_disc)?;
+        if thrower.x < SIDELINE_X_MIN {
+            return None;
+        }
+        let (tx, ty) = (thrower.x, thrower.y);
+        let key = Fingerprint::new(
+            CachedLayer::MarkingDifficulty,
+            num_cells_x,
+            num_cells_y,
+            grid_size,
+        )
+        .f64(tx)
+        .f64(ty)
+        .disc(disc)
+        .value(params)
+        .finish();
+        let values = self.get_or_compute(CachedLayer::MarkingDifficulty, key, || {
+            get_marking_difficulty_layer(num_cells_x, num_cells_y, grid_size, players, disc, params)
+                .map(|(values, _, _)| values)
+                .unwrap_or_default()
+        });
+        Some((values, tx, ty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(bytes: &[u8]) -> Key {
+        Key::from(bytes)
+    }
+
+    fn grid(v: f64) -> Arc<Grid> {
+        Arc::new(vec![vec![v]])
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut lru = Lru::default();
+        lru.insert(key(b"a"), grid(1.0), 2);
+        lru.insert(key(b"b"), grid(2.0), 2);
+        // Touching `a` leaves `b` as the oldest.
+        assert_eq!(lru.get(b"a").unwrap()[0][0], 1.0);
+        lru.insert(key(b"c"), grid(3.0), 2);
+
+        assert_eq!(lru.evictions, 1);
+        assert_eq!(lru.entries.len(), 2);
+        assert!(lru.get(b"b").is_none());
+        assert_eq!(lru.get(b"a").unwrap()[0][0], 1.0);
+        assert_eq!(lru.get(b"c").unwrap()[0][0], 3.0);
+    }
+
+    #[test]
+    fn reinserting_a_key_replaces_it_without_evicting() {
+        let mut lru = Lru::default();
+        lru.insert(key(b"a"), grid(1.0), 2);
+        lru.insert(key(b"a"), grid(4.0), 2);
+        assert_eq!(lru.evictions, 0);
+        assert_eq!(lru.entries.len(), 1);
+        assert_eq!(lru.recency.len(), 1);
+        assert_eq!(lru.get(b"a").unwrap()[0][0], 4.0);
+    }
+
+    #[test]
+    fn keys_differing_in_any_byte_do_not_share_an_entry() {
+        let cache = LayerCache::new(4);
+        let first = cache.get_or_compute(CachedLayer::Catch, Ok(key(b"ab")), || vec![vec![1.0]]);
+        let second = cache.get_or_compute(CachedLayer::Catch, Ok(key(b"ac")), || vec![vec![2.0]]);
+        let again = cache.get_or_compute(CachedLayer::Catch, Ok(key(b"ab")), || vec![vec![9.0]]);
+        assert_eq!((first[0][0], second[0][0], again[0][0]), (1.0, 2.0, 1.0));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let cache = LayerCache::disabled();
+        cache.get_or_compute(CachedLayer::Difficulty, Ok(key(b"a")), || vec![vec![1.0]]);
+        let v = cache.get_or_compute(CachedLayer::Difficulty, Ok(key(b"a")), || vec![vec![2.0]]);
+        assert_eq!(v[0][0], 2.0);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
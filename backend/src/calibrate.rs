@@ -0,0 +1,228 @@
+//! Fit heat-map parameters to recorded throws.
+//!
+//! Each record is a game state, the target the thrower actually chose and
+//! whether the throw was completed.  The model is scored by two terms:
+//!
+//!   * **choice** — the thrower picks a cell with probability proportional to
+//!     its combined heat-map value (the same rule `position_offender_optimal`
+//!     samples from), so the log-likelihood is `ln(v_target / Σ v)`;
+//!   * **outcome** — the combined value at the target, which lies in [0, 1],
+//!     is read as the completion probability: `ln v` for completions and
+//!     `ln(1 − v)` for turnovers.
+//!
+//! Nelder–Mead maximises the summed log-likelihood over the parameters listed
+//! in `FITTED`; everything else keeps its starting value.  The difficulty
+//! layer's distance penalty is fitted through `postNormDivisor` rather than
+//! `distanceScale`, which its normalisation cancels.
+
+use crate::cache::LayerCache;
+use crate::heatmap::{calculate_heat_map, HeatMapParams, SIDELINE_X_MIN};
+use crate::models::{HeatMapModes, LabelledThrow};
+use crate::optimize::nelder_mead;
+
+// ============================================================================
+// CALIBRATION CONSTANTS
+// ============================================================================
+
+/// Cell values and probabilities are floored at this before taking logs so a
+/// single zero-valued cell (e.g. fully covered) cannot give −∞.
+const CALIBRATION_EPSILON: f64 = 1e-6;
+
+/// Stop once the simplex's log-likelihood spread is below this.
+const CALIBRATION_TOLERANCE: f64 = 1e-6;
+
+/// A fitted parameter: name, accessor, lower / upper bound, initial step and
+/// an optional note shown with the results.
+struct Fitted {
+    name: &'static str,
+    field: fn(&mut HeatMapParams) -> &mut f64,
+    min: f64,
+    max: f64,
+    step: f64,
+    note: Option<&'static str>,
+}
+
+/// Parameters adjusted by the fit.  The difficulty layer is normalised by its
+/// own maximum, which cancels `distanceScale`; `postNormDivisor` is the
+/// parameter that actually changes how strongly distance is penalised.
+const FITTED: [Fitted; 5] = [
+    Fitted {
+        name: "catch.minPassDistanceYards",
+        field: |p| &mut p.catch.min_pass_distance_yards,
+        min: 0.5,
+        max: 20.0,
+        step: 1.0,
+        note: None,
+    },
+    Fitted {
+        name: "catch.shortPassExponent",
+        field: |p| &mut p.catch.short_pass_exponent,
+        min: 0.5,
+        max: 8.0,
+        step: 0.5,
+        note: None,
+    },
+    Fitted {
+        name: "difficulty.postNormDivisor",
+        field: |p| &mut p.difficulty.post_norm_divisor,
+        min: 1.0,
+        max: 10.0,
+        step: 0.5,
+        note: Some(
+            "difficulty.postNormDivisor is fitted in place of difficulty.distanceScale, \
+             which the difficulty layer's normalisation cancels",
+        ),
+    },
+    Fitted {
+        name: "mark.easyAngleRadians",
+        field: |p| &mut p.mark.easy_angle_radians,
+        min: 0.05,
+        max: std::f64::consts::PI,
+        step: 0.1,
+        note: None,
+    },
+    Fitted {
+        name: "coverage.defenderHandicapYards",
+        field: |p| &mut p.coverage.defender_handicap_yards,
+        min: -5.0,
+        max: 10.0,
+        step: 1.0,
+        note: None,
+    },
+];
+
+/// Outcome of a calibration run.
+#[derive(Debug, Clone)]
+pub struct CalibrationReport {
+    pub params: HeatMapParams,
+    pub initial_log_likelihood: f64,
+    pub final_log_likelihood: f64,
+    /// Records that had a thrower on the field and could be scored.
+    pub records_used: usize,
+    pub iterations: usize,
+    /// `(name, before, after)` for every fitted parameter.
+    pub changes: Vec<(&'static str, f64, f64)>,
+    /// Caveats about what was fitted, to show alongside the changes.
+    pub notes: Vec<&'static str>,
+}
+
+/// Fit the parameters in `FITTED` to `records`, starting from `start`.
+pub fn calibrate(
+    records: &[LabelledThrow],
+    grid_size: f64,
+    start: &HeatMapParams,
+    max_iterations: usize,
+) -> CalibrationReport {
+    let mut start = start.clone();
+    let x0: Vec<f64> = FITTED
+        .iter()
+        .map(|f| (f.field)(&mut start).clamp(f.min, f.max))
+        .collect();
+    let steps: Vec<f64> = FITTED.iter().map(|f| f.step).collect();
+
+    let (initial_log_likelihood, records_used) =
+        log_likelihood(records, grid_size, &with_values(&start, &x0));
+
+    // Nelder–Mead minimises, so negate the log-likelihood.
+    let minimum = nelder_mead(
+        |x| -log_likelihood(records, grid_size, &with_values(&start, x)).0,
+        &x0,
+        &steps,
+        max_iterations,
+        CALIBRATION_TOLERANCE,
+    );
+
+    let params = with_values(&start, &minimum.x);
+    let mut fitted = params.clone();
+    let changes = FITTED
+        .iter()
+        .zip(&x0)
+        .map(|(f, &before)| (f.name, before, *(f.field)(&mut fitted)))
+        .collect();
+
+    CalibrationReport {
+        params,
+        initial_log_likelihood,
+        final_log_likelihood: -minimum.value,
+        records_used,
+        iterations: minimum.iterations,
+        changes,
+        notes: FITTED.iter().filter_map(|f| f.note).collect(),
+    }
+}
+
+/// Summed choice + outcome log-likelihood of `records` under `params`, and
+/// the number of records scored.  Records without a thrower on the field
+/// (none, or one in the sideline area) are skipped.
+pub fn log_likelihood(
+    records: &[LabelledThrow],
+    grid_size: f64,
+    params: &HeatMapParams,
+) -> (f64, usize) {
+    let modes = HeatMapModes {
+        catch: true,
+        difficulty: true,
+        marking_difficulty: true,
+        coverage: true,
+        completion: false,
+        epv: false,
+        expected_gain: false,
+        reset_space: false,
+    };
+
+    // Every trial has its own parameters, so its layers are never asked for
+    // again; caching them would only evict useful entries.
+    let cache = LayerCache::disabled();
+    let mut total = 0.0_f64;
+    let mut used = 0usize;
+    for record in records {
+        let players = &record.game_state.players;
+        if !players.iter().any(|p| p.has_disc && p.x >= SIDELINE_X_MIN) {
+            continue;
+        }
+        let params = params.for_field(&record.game_state.field);
+        let Some(map) = calculate_heat_map(
+            &record.game_state,
+            &modes,
+            false,
+            grid_size,
+            &params,
+            &cache,
+        ) else {
+            continue;
+        };
+        let cells_x = map.values.len();
+        let cells_y = map.values.first().map_or(0, Vec::len);
+        if cells_x == 0 || cells_y == 0 {
+            continue;
+        }
+
+        let xi = ((record.target_x / grid_size).floor().max(0.0) as usize).min(cells_x - 1);
+        let yi = ((record.target_y / grid_size).floor().max(0.0) as usize).min(cells_y - 1);
+        let target = map.values[xi][yi].max(CALIBRATION_EPSILON);
+        used += 1;
+        let sum: f64 = map
+            .values
+            .iter()
+            .flatten()
+            .map(|v| v.max(CALIBRATION_EPSILON))
+            .sum();
+
+        total += (target / sum).ln();
+        total += if record.completed {
+            target.min(1.0 - CALIBRATION_EPSILON).ln()
+        } else {
+            (1.0 - target).max(CALIBRATION_EPSILON).ln()
+        };
+    }
+    (total, used)
+}
+
+/// `base` with the fitted parameters set from `x`, clamped to their bounds.
+fn with_values(base: &HeatMapParams, x: &[f64]) -> HeatMapParams {
+    let mut params = base.clone();
+    for (f, &v) in FITTED.iter().zip(x) {
+        *(f.field)(&mut params) = v.clamp(f.min, f.max);
+    }
+    params
+}
@@ -0,0 +1,195 @@
+//! What this server supports, for `GET /api/capabilities`.
+//!
+//! Clients use it to build their layer toggles, field menu and grid-size
+//! control from the server they are talking to instead of hard-coding them.
+//! The tables below describe code elsewhere; keep them in step when adding a
+//! layer (`HeatMapModes`) or a route (`main.rs`).
+
+use crate::animate::ANIMATION_MAX_TICKS;
+use crate::batch::BATCH_MAX_ENTRIES;
+use crate::config::Config;
+use crate::export::{BinaryEncoding, ExportFormat};
+use crate::field::{FieldPreset, FieldPresetInfo, MAX_FIELD_DIMENSION_YARDS};
+use crate::game::DEFENDER_SEARCH_MAX_RADIUS_YARDS;
+use crate::heatmap::{MAX_GRID_SIZE, MIN_GRID_SIZE};
+use crate::models::{
+    CapabilitiesResponse, CombinationModeInfo, EndpointInfo, LayerInfo, Limits, MAX_STALL_COUNT,
+};
+use crate::simulate::{SIMULATION_MAX_PASSES, SIMULATION_MAX_RUNS};
+use crate::tracking::{TRACKING_MAX_BODY_BYTES, TRACKING_MAX_FRAMES};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const LAYERS: [LayerInfo; 8] = [
+    LayerInfo {
+        name: "catch",
+        description: "How valuable a catch at each spot is: downfield progress, centre of the \
+                      field, end zone",
+        min: 0.0,
+        max: 1.0,
+        in_sum: true,
+    },
+    LayerInfo {
+        name: "difficulty",
+        description: "How hard the throw from the disc is; inverted (1 − v) when combined",
+        min: 0.0,
+        max: 1.0,
+        in_sum: true,
+    },
+    LayerInfo {
+        name: "markingDifficulty",
+        description: "How much the mark takes away the throw to each spot",
+        min: 0.0,
+        max: 1.0,
+        in_sum: true,
+    },
+    LayerInfo {
+        name: "coverage",
+        description: "Whether each spot is open (1), half covered (0.5) or covered (0) by the \
+                      downfield defence",
+        min: 0.0,
+        max: 1.0,
+        in_sum: true,
+    },
+    LayerInfo {
+        name: "completion",
+        description: "Probability a throw to each spot is caught",
+        min: 0.0,
+        max: 1.0,
+        in_sum: false,
+    },
+    LayerInfo {
+        name: "epv",
+        description: "Chance the offence eventually scores with the disc at each spot",
+        min: 0.0,
+        max: 1.0,
+        in_sum: false,
+    },
+    LayerInfo {
+        name: "expectedGain",
+        description: "Completion-weighted EPV gain of a throw to each spot; never combined",
+        min: -1.0,
+        max: 1.0,
+        in_sum: false,
+    },
+    LayerInfo {
+        name: "resetSpace",
+        description: "Dump and swing space behind the disc",
+        min: 0.0,
+        max: 1.0,
+        in_sum: false,
+    },
+];
+
+const COMBINATION_MODES: [CombinationModeInfo; 2] = [
+    CombinationModeInfo {
+        name: "product",
+        normalize: false,
+        description: "Product of every enabled layer; `mode` is \"combined\", or the layer's \
+                      name when only one is enabled",
+    },
+    CombinationModeInfo {
+        name: "normalizedProduct",
+        normalize: true,
+        description: "The product, min-max scaled to [0, 1] so the colour range is used fully",
+    },
+];
+
+/// Every route the server answers, in the order the banner lists them.
+pub const ENDPOINTS: [EndpointInfo; 34] = [
+    endpoint("GET", "/api/health"),
+    endpoint("GET", "/api/capabilities"),
+    endpoint("POST", "/api/heatmap"),
+    endpoint("POST", "/api/heatmap-sum"),
+    endpoint("POST", "/api/heatmap-batch"),
+    endpoint("POST", "/api/simulate"),
+    endpoint("POST", "/api/animate"),
+    endpoint("GET", "/api/field-presets"),
+    endpoint("POST", "/api/position-defender"),
+    endpoint("POST", "/api/position-offender"),
+    endpoint("POST", "/api/position-stack"),
+    endpoint("POST", "/api/position-defender-learned"),
+    endpoint("POST", "/api/position-defender-hybrid"),
+    endpoint("GET", "/api/training/examples"),
+    endpoint("POST", "/api/training/examples"),
+    endpoint("DELETE", "/api/training/examples"),
+    endpoint("DELETE", "/api/training/examples/:id"),
+    endpoint("GET", "/api/training/evaluate"),
+    endpoint("GET", "/api/scenarios"),
+    endpoint("POST", "/api/scenarios"),
+    endpoint("GET", "/api/scenarios/:id"),
+    endpoint("PUT", "/api/scenarios/:id"),
+    endpoint("DELETE", "/api/scenarios/:id"),
+    endpoint("POST", "/api/tracking/import"),
+    endpoint("POST", "/api/tracking/heatmap-sums"),
+    endpoint("GET", "/api/recordings"),
+    endpoint("POST", "/api/recordings/start"),
+    endpoint("POST", "/api/recordings/stop"),
+    endpoint("GET", "/api/recordings/:id"),
+    endpoint("POST", "/api/recordings/:id/replay"),
+    endpoint("GET", "/api/cache"),
+    endpoint("DELETE", "/api/cache"),
+    endpoint("GET", "/api/session"),
+    endpoint("GET", "/metrics"),
+];
+
+const fn endpoint(method: &'static str, path: &'static str) -> EndpointInfo {
+    EndpointInfo { method, path }
+}
+
+/// `ENDPOINTS` as `(methods, path)` lines for the start-up banner, with the
+/// methods of consecutive entries for the same path joined.
+pub fn endpoint_lines() -> Vec<(String, &'static str)> {
+    let mut lines: Vec<(String, &'static str)> = Vec::new();
+    for e in ENDPOINTS {
+        match lines.last_mut() {
+            Some((methods, path)) if *path == e.path => {
+                methods.push(' ');
+                methods.push_str(e.method);
+            }
+            _ => lines.push((e.method.to_string(), e.path)),
+        }
+    }
+    lines
+}
+
+pub fn capabilities(config: &Config) -> CapabilitiesResponse {
+    let mut default_params = config.model.clone();
+    let epv_table_loaded = default_params.epv.table.take().is_some();
+    CapabilitiesResponse {
+        version: VERSION,
+        layers: LAYERS.to_vec(),
+        combination_modes: COMBINATION_MODES.to_vec(),
+        field_presets: FieldPreset::ALL
+            .into_iter()
+            .map(FieldPresetInfo::from)
+            .collect(),
+        export_formats: vec![
+            ExportFormat::Json,
+            ExportFormat::Csv,
+            ExportFormat::Npy,
+            ExportFormat::GeoJson,
+        ],
+        binary_encodings: BinaryEncoding::ALL
+            .into_iter()
+            .map(BinaryEncoding::media_type)
+            .collect(),
+        default_params,
+        epv_table_loaded,
+        limits: Limits {
+            min_grid_size: MIN_GRID_SIZE,
+            max_grid_size: MAX_GRID_SIZE,
+            default_grid_size: config.default_grid_size,
+            max_field_dimension_yards: MAX_FIELD_DIMENSION_YARDS,
+            max_defender_search_radius_yards: DEFENDER_SEARCH_MAX_RADIUS_YARDS,
+            max_batch_entries: BATCH_MAX_ENTRIES,
+            max_simulations: SIMULATION_MAX_RUNS,
+            max_simulation_passes: SIMULATION_MAX_PASSES,
+            max_animation_ticks: ANIMATION_MAX_TICKS,
+            max_tracking_body_bytes: TRACKING_MAX_BODY_BYTES,
+            max_tracking_frames: TRACKING_MAX_FRAMES,
+            max_stall: MAX_STALL_COUNT,
+        },
+        endpoints: ENDPOINTS.to_vec(),
+    }
+}
@@ -0,0 +1,437 @@
+//! Command-line interface.
+//!
+//! Without a subcommand the binary starts the HTTP server, configured by the
+//! flags in `ServerArgs` (see `config` for precedence).  The `eval`
+//! subcommand runs a single calculation on a `GameState` read from a file or
+//! stdin and writes the result to stdout, so analyses can be scripted without
+//! a server.  `calibrate` and `score-completion` work on JSON Lines of
+//! recorded throws instead.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::cache::LayerCache;
+use crate::calibrate::calibrate;
+use crate::completion::score;
+use crate::config::Config;
+use crate::export::{export, ExportFormat};
+use crate::game::{
+    position_defender_optimal, position_offender_optimal, position_offender_stack, validate_search,
+};
+use crate::heatmap::{calculate_heat_map, combined_heat_map_sum, validate_grid_size};
+use crate::models::{
+    DefenderSearch, GameState, HeatMapData, HeatMapModes, HeatMapSumResponse, LabelledThrow,
+    PositionResponse, SearchShape,
+};
+use crate::telemetry::LogFormat;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Ultimate frisbee heat-map backend")]
+pub struct Cli {
+    /// TOML config file.
+    #[arg(long, global = true, env = "UFB_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub server: ServerArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Overrides for the config file.  Each flag can also be set through the
+/// listed environment variable.
+#[derive(Debug, Default, Args)]
+pub struct ServerArgs {
+    /// Address to bind.
+    #[arg(long, env = "UFB_HOST")]
+    pub host: Option<String>,
+
+    /// Port to listen on.
+    #[arg(long, env = "UFB_PORT")]
+    pub port: Option<u16>,
+
+    /// Allowed CORS origins (comma separated); `*` allows any.
+    #[arg(
+        long = "allowed-origins",
+        env = "UFB_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    pub allowed_origins: Option<Vec<String>>,
+
+    /// Grid size (yards) used when a request omits `gridSize`.
+    #[arg(long, global = true, env = "UFB_DEFAULT_GRID_SIZE")]
+    pub default_grid_size: Option<f64>,
+
+    /// Serve the web UI from this directory at `/`.
+    #[arg(long, env = "UFB_STATIC_DIR")]
+    pub static_dir: Option<PathBuf>,
+
+    /// Serve the web UI embedded in the binary at `/`.
+    #[arg(long, env = "UFB_EMBEDDED_FRONTEND")]
+    pub embedded_frontend: bool,
+
+    /// Directory for server-side data (scenario library, training examples).
+    #[arg(long, env = "UFB_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// JSON heat-map parameter file (e.g. written by `calibrate`).
+    #[arg(long, global = true, env = "UFB_MODEL_FILE")]
+    pub model_file: Option<PathBuf>,
+
+    /// CSV table of `x,y,epv` used by the EPV and expected-gain layers.
+    #[arg(long, global = true, env = "UFB_EPV_TABLE")]
+    pub epv_table: Option<PathBuf>,
+
+    /// Heat-map layers kept in the in-process cache; 0 disables it.
+    #[arg(long, global = true, env = "UFB_LAYER_CACHE_CAPACITY")]
+    pub layer_cache_capacity: Option<usize>,
+
+    /// Request log format.  Filter with `RUST_LOG` (default `info`).
+    #[arg(long, value_enum, env = "UFB_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Evaluate a game state and print the result instead of serving.
+    Eval(EvalArgs),
+    /// Fit heat-map parameters to recorded throws and write a model file.
+    Calibrate(CalibrateArgs),
+    /// Compare completion probabilities with recorded throw outcomes.
+    ScoreCompletion(ScoreCompletionArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct EvalArgs {
+    /// What to compute.
+    #[arg(value_enum)]
+    pub what: EvalTarget,
+
+    /// `GameState` JSON file; `-` or omitted reads stdin.
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+
+    /// Heat-map cell size in yards.  Defaults to the configured grid size.
+    #[arg(short, long)]
+    pub grid_size: Option<f64>,
+
+    /// Player label for the positioning targets (e.g. "1").
+    #[arg(short, long)]
+    pub label: Option<String>,
+
+    /// Layers combined by `heatmap` (comma separated).  Defaults to catch,
+    /// difficulty, marking-difficulty and coverage.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub layers: Vec<Layer>,
+
+    /// Skip min-max normalisation of the `heatmap` output.
+    #[arg(long)]
+    pub no_normalize: bool,
+
+    /// Search region around the offender for `position-defender`.
+    #[arg(long, value_enum, default_value_t = Shape::Circle)]
+    pub search_shape: Shape,
+
+    /// Radius of the search region in yards (default 5).
+    #[arg(long)]
+    pub search_radius: Option<f64>,
+
+    /// Refine the best cell with a continuous search.
+    #[arg(long)]
+    pub refine: bool,
+
+    /// Only consider spots the defender reaches within this many seconds.
+    #[arg(long)]
+    pub reaction_seconds: Option<f64>,
+
+    /// Defender top speed (yards per second) for `--reaction-seconds`.
+    #[arg(long)]
+    pub max_speed: Option<f64>,
+
+    /// Defender acceleration (yards per second²) for `--reaction-seconds`.
+    #[arg(long)]
+    pub acceleration: Option<f64>,
+
+    /// Output format.  Everything but `json` is only valid for `heatmap`.
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct CalibrateArgs {
+    /// JSON Lines of `{ gameState, targetX, targetY, completed }`; `-` or
+    /// omitted reads stdin.
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+
+    /// Where to write the fitted parameters (JSON); stdout when omitted.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Heat-map cell size in yards.  Defaults to the configured grid size.
+    #[arg(short, long)]
+    pub grid_size: Option<f64>,
+
+    /// Maximum optimiser iterations.
+    #[arg(long, default_value_t = 300)]
+    pub max_iterations: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct ScoreCompletionArgs {
+    /// JSON Lines of `{ gameState, targetX, targetY, completed }`; `-` or
+    /// omitted reads stdin.
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EvalTarget {
+    /// Combined heat map (same as `POST /api/heatmap`).
+    Heatmap,
+    /// Combined heat-map sum (same as `POST /api/heatmap-sum`).
+    Sum,
+    /// Optimal position for the defender with `--label`.
+    PositionDefender,
+    /// Sampled position for the offender with `--label`.
+    PositionOffender,
+    /// Stack position for the first downfield offender.
+    PositionStack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layer {
+    Catch,
+    Difficulty,
+    MarkingDifficulty,
+    Coverage,
+    Completion,
+    Epv,
+    ExpectedGain,
+    ResetSpace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shape {
+    Circle,
+    GoalSide,
+    Under,
+    Deep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    /// One line per x cell, comma-separated y values (`values[x][y]`).
+    Csv,
+    /// `x,y,value` per cell centre, with a header.
+    CsvLong,
+    /// NumPy `.npy` array of shape `(nx, ny)`.
+    Npy,
+    /// GeoJSON polygon per cell, in field yards.
+    #[value(name = "geojson")]
+    GeoJson,
+}
+
+/// Run the `eval` subcommand.  Errors are returned as messages for `main` to
+/// print.
+pub fn run_eval(args: EvalArgs, config: &Config) -> Result<(), String> {
+    if args.format != OutputFormat::Json && args.what != EvalTarget::Heatmap {
+        return Err("--format other than json is only supported for `heatmap`".to_string());
+    }
+    let grid_size = args.grid_size.unwrap_or(config.default_grid_size);
+    validate_grid_size(grid_size).map_err(|e| format!("--grid-size {e}"))?;
+    let search = search_from_args(&args);
+    validate_search(&search)?;
+    let mut gs = read_game_state(args.input.as_ref())?;
+    let params = &config.model.for_field(&gs.field).into_owned();
+    let cache = &LayerCache::new(config.layer_cache_capacity);
+    let label = || {
+        args.label
+            .as_deref()
+            .ok_or_else(|| "--label is required for positioning targets".to_string())
+    };
+
+    let mut out = io::stdout().lock();
+    let json = match args.what {
+        EvalTarget::Heatmap => {
+            let data = calculate_heat_map(
+                &gs,
+                &modes_from_layers(&args.layers),
+                !args.no_normalize,
+                grid_size,
+                params,
+                cache,
+            );
+            let export_format = match args.format {
+                OutputFormat::Json => None,
+                OutputFormat::Csv => {
+                    let data = data.ok_or("no heat map (no layers or no thrower)")?;
+                    return write_csv(&mut out, &data).map_err(|e| e.to_string());
+                }
+                OutputFormat::CsvLong => Some(ExportFormat::Csv),
+                OutputFormat::Npy => Some(ExportFormat::Npy),
+                OutputFormat::GeoJson => Some(ExportFormat::GeoJson),
+            };
+            if let Some(format) = export_format {
+                let data = data.ok_or("no heat map (no layers or no thrower)")?;
+                return out
+                    .write_all(&export(&data, format))
+                    .map_err(|e| e.to_string());
+            }
+            serde_json::to_string_pretty(&data)
+        }
+        EvalTarget::Sum => {
+            let sum = combined_heat_map_sum(&gs, grid_size, params, cache);
+            serde_json::to_string_pretty(&HeatMapSumResponse { sum })
+        }
+        EvalTarget::PositionDefender => {
+            let pos =
+                position_defender_optimal(&mut gs, grid_size, label()?, params, cache, &search);
+            serde_json::to_string_pretty(&pos.map(|(x, y)| PositionResponse { x, y }))
+        }
+        EvalTarget::PositionOffender => {
+            let pos = position_offender_optimal(&mut gs, grid_size, label()?, params, cache);
+            serde_json::to_string_pretty(&pos.map(|(x, y)| PositionResponse { x, y }))
+        }
+        EvalTarget::PositionStack => {
+            let pos = position_offender_stack(&mut gs);
+            serde_json::to_string_pretty(&pos.map(|(x, y)| PositionResponse { x, y }))
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    writeln!(out, "{json}").map_err(|e| e.to_string())
+}
+
+/// Run the `calibrate` subcommand: fit, report progress on stderr and write
+/// the parameter file.
+pub fn run_calibrate(args: CalibrateArgs, config: &Config) -> Result<(), String> {
+    let grid_size = args.grid_size.unwrap_or(config.default_grid_size);
+    validate_grid_size(grid_size).map_err(|e| format!("--grid-size {e}"))?;
+
+    let records = read_throws(args.input.as_ref())?;
+
+    let report = calibrate(&records, grid_size, &config.model, args.max_iterations);
+    if report.records_used == 0 {
+        return Err("no record has a thrower (`hasDisc`) on the field".to_string());
+    }
+
+    eprintln!(
+        "records: {} used of {}   iterations: {}",
+        report.records_used,
+        records.len(),
+        report.iterations
+    );
+    eprintln!(
+        "log-likelihood: {:.4} -> {:.4}  (per record {:.4} -> {:.4})",
+        report.initial_log_likelihood,
+        report.final_log_likelihood,
+        report.initial_log_likelihood / report.records_used as f64,
+        report.final_log_likelihood / report.records_used as f64
+    );
+    for (name, before, after) in &report.changes {
+        eprintln!("  {name:<32} {before:>10.4} -> {after:>10.4}");
+    }
+    for note in &report.notes {
+        eprintln!("note: {note}");
+    }
+
+    let json = serde_json::to_string_pretty(&report.params).map_err(|e| e.to_string())?;
+    match &args.output {
+        Some(path) => fs::write(path, json + "\n")
+            .map_err(|e| format!("cannot write {}: {e}", path.display())),
+        None => writeln!(io::stdout(), "{json}").map_err(|e| e.to_string()),
+    }
+}
+
+/// Run the `score-completion` subcommand: print Brier score, log loss and
+/// predicted vs observed completion rate as JSON.
+pub fn run_score_completion(args: ScoreCompletionArgs, config: &Config) -> Result<(), String> {
+    let records = read_throws(args.input.as_ref())?;
+    let result = score(&records, &config.model).ok_or("no records to score")?;
+    let json = serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?;
+    writeln!(io::stdout(), "{json}").map_err(|e| e.to_string())
+}
+
+/// Recorded throws, one JSON object per non-empty line.
+fn read_throws(input: Option<&PathBuf>) -> Result<Vec<LabelledThrow>, String> {
+    let text = read_input(input)?;
+    let mut records = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: LabelledThrow = serde_json::from_str(line)
+            .map_err(|e| format!("line {}: invalid record: {e}", n + 1))?;
+        records.push(record);
+    }
+    if records.is_empty() {
+        return Err("no records in input".to_string());
+    }
+    Ok(records)
+}
+
+fn read_game_state(input: Option<&PathBuf>) -> Result<GameState, String> {
+    let text = read_input(input)?;
+    serde_json::from_str(&text).map_err(|e| format!("invalid GameState JSON: {e}"))
+}
+
+/// Contents of `input`, or stdin for `None` / `-`.
+fn read_input(input: Option<&PathBuf>) -> Result<String, String> {
+    let text = match input {
+        Some(path) if path.as_os_str() != "-" => {
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?
+        }
+        _ => {
+            let mut buf = String::new();
+            io::stdin()
+                .read_to_string(&mut buf)
+                .map_err(|e| format!("cannot read stdin: {e}"))?;
+            buf
+        }
+    };
+    Ok(text)
+}
+
+fn modes_from_layers(layers: &[Layer]) -> HeatMapModes {
+    let all = layers.is_empty();
+    HeatMapModes {
+        catch: all || layers.contains(&Layer::Catch),
+        difficulty: all || layers.contains(&Layer::Difficulty),
+        marking_difficulty: all || layers.contains(&Layer::MarkingDifficulty),
+        coverage: all || layers.contains(&Layer::Coverage),
+        completion: layers.contains(&Layer::Completion),
+        epv: layers.contains(&Layer::Epv),
+        expected_gain: layers.contains(&Layer::ExpectedGain),
+        reset_space: layers.contains(&Layer::ResetSpace),
+    }
+}
+
+fn search_from_args(args: &EvalArgs) -> DefenderSearch {
+    DefenderSearch {
+        shape: match args.search_shape {
+            Shape::Circle => SearchShape::Circle,
+            Shape::GoalSide => SearchShape::GoalSide,
+            Shape::Under => SearchShape::Under,
+            Shape::Deep => SearchShape::Deep,
+        },
+        radius_yards: args.search_radius,
+        refine: args.refine,
+        reaction_seconds: args.reaction_seconds,
+        max_speed_yps: args.max_speed,
+        acceleration_yps2: args.acceleration,
+    }
+}
+
+fn write_csv(out: &mut impl Write, data: &HeatMapData) -> io::Result<()> {
+    for column in &data.values {
+        let line: Vec<String> = column.iter().map(|v| v.to_string()).collect();
+        writeln!(out, "{}", line.join(","))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,310 @@
+//! Completion-probability layer.
+//!
+//! Unlike the other layers, which are unitless scores multiplied together,
+//! this one is an actual probability that a throw to the cell is caught, so it
+//! can be checked against recorded outcomes.  It is the product of four
+//! independent factors:
+//!
+//!   * **throw type** — open-side, break-side (around the mark) or huck, each
+//!     with its own base completion rate;
+//!   * **distance** — a logistic fall-off around the thrower's range, which
+//!     the wind stretches downwind and shrinks upwind;
+//!   * **mark** — how much of the throwing lane the mark takes away;
+//!   * **arrival** — whether a receiver reaches the spot in time (the disc
+//!     hangs longer into the wind), whether the nearest defender can reach it
+//!     before the disc does and, if so, who wins the race.
+
+// Grids are indexed by cell coordinates, as in `heatmap`.
+#![allow(clippy::needless_range_loop)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::heatmap::{
+    calculate_ease_at, calculate_marking_difficulty_at, HeatMapParams, MarkParams, SIDELINE_X_MIN,
+};
+use crate::models::{GameState, LabelledThrow, Player};
+use crate::wind::{ground_speed_factor, range_factor};
+
+// ============================================================================
+// COMPLETION LAYER CONSTANTS
+// Rough club-level numbers; calibrate against recorded throws for a team.
+// ============================================================================
+
+/// Base completion rate of an uncontested, in-range throw to the open side.
+const COMPLETION_OPEN_RATE: f64 = 0.98;
+
+/// Base completion rate of a throw around the mark (break side).
+const COMPLETION_BREAK_RATE: f64 = 0.92;
+
+/// Base completion rate of a huck.
+const COMPLETION_HUCK_RATE: f64 = 0.85;
+
+/// Throws at least this long (yards) count as hucks.
+const COMPLETION_HUCK_DISTANCE_YARDS: f64 = 30.0;
+
+/// Distance (yards) at which the distance factor is 0.5.
+const COMPLETION_RANGE_YARDS: f64 = 55.0;
+
+/// Width (yards) of the logistic fall-off around `RANGE_YARDS`.
+const COMPLETION_RANGE_SPREAD_YARDS: f64 = 6.0;
+
+/// Share of throws lost to a block when throwing straight into a close mark.
+const COMPLETION_MARK_BLOCK_RATE: f64 = 0.6;
+
+/// Average disc speed in yards per second.
+const COMPLETION_DISC_SPEED_YPS: f64 = 18.0;
+
+/// Time from deciding to throw until the disc leaves the hand (seconds).
+const COMPLETION_RELEASE_SECONDS: f64 = 0.3;
+
+/// Player running speed in yards per second (offence and defence alike).
+const COMPLETION_PLAYER_SPEED_YPS: f64 = 7.0;
+
+/// How long a receiver may arrive after the disc and still catch it — the
+/// disc floats and can be read (seconds).
+const COMPLETION_RECEIVER_GRACE_SECONDS: f64 = 0.4;
+
+/// Width (seconds) of the logistic used for the arrival races.
+const COMPLETION_TIME_SPREAD_SECONDS: f64 = 0.35;
+
+/// Share of contested throws a defender breaks up even when the receiver
+/// gets there first; rises toward 1 as the defender gets there earlier.
+const COMPLETION_CONTEST_BLOCK_RATE: f64 = 0.3;
+
+// ============================================================================
+// Runtime parameters
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct CompletionParams {
+    pub open_rate: f64,
+    pub break_rate: f64,
+    pub huck_rate: f64,
+    pub huck_distance_yards: f64,
+    pub range_yards: f64,
+    pub range_spread_yards: f64,
+    pub mark_block_rate: f64,
+    pub disc_speed_yps: f64,
+    pub release_seconds: f64,
+    pub player_speed_yps: f64,
+    pub receiver_grace_seconds: f64,
+    pub time_spread_seconds: f64,
+    pub contest_block_rate: f64,
+}
+
+impl Default for CompletionParams {
+    fn default() -> Self {
+        Self {
+            open_rate: COMPLETION_OPEN_RATE,
+            break_rate: COMPLETION_BREAK_RATE,
+            huck_rate: COMPLETION_HUCK_RATE,
+            huck_distance_yards: COMPLETION_HUCK_DISTANCE_YARDS,
+            range_yards: COMPLETION_RANGE_YARDS,
+            range_spread_yards: COMPLETION_RANGE_SPREAD_YARDS,
+            mark_block_rate: COMPLETION_MARK_BLOCK_RATE,
+            disc_speed_yps: COMPLETION_DISC_SPEED_YPS,
+            release_seconds: COMPLETION_RELEASE_SECONDS,
+            player_speed_yps: COMPLETION_PLAYER_SPEED_YPS,
+            receiver_grace_seconds: COMPLETION_RECEIVER_GRACE_SECONDS,
+            time_spread_seconds: COMPLETION_TIME_SPREAD_SECONDS,
+            contest_block_rate: COMPLETION_CONTEST_BLOCK_RATE,
+        }
+    }
+}
+
+/// How a throw to a given target would be thrown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThrowType {
+    Open,
+    Break,
+    Huck,
+}
+
+/// Accuracy of the model over a set of recorded throws.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionScore {
+    pub throws: usize,
+    /// Mean squared error of the predicted probability (lower is better).
+    pub brier: f64,
+    /// Mean negative log-likelihood of the outcomes (lower is better).
+    pub log_loss: f64,
+    pub mean_predicted: f64,
+    pub observed_rate: f64,
+}
+
+// ============================================================================
+// Per-target calculation
+// ============================================================================
+
+/// Probability that a throw from the current thrower to `(x, y)` is caught.
+/// Without a thrower on the field the throw starts at the disc, unmarked.
+pub fn completion_probability(
+    game_state: &GameState,
+    x: f64,
+    y: f64,
+    model: &HeatMapParams,
+) -> f64 {
+    let mark = &model.mark;
+    let params = &model.completion;
+    let disc = &game_state.disc;
+    let players = &game_state.players;
+    let thrower = players.iter().find(|p| p.has_disc && p.x >= SIDELINE_X_MIN);
+    let (tx, ty) = thrower.map_or((disc.x, disc.y), |p| (p.x, p.y));
+
+    let distance = ((x - tx).powi(2) + (y - ty).powi(2)).sqrt();
+    let throw_type = classify_throw(thrower, x, y, distance, mark, params);
+
+    // ── Throw type ──────────────────────────────────────────────────────────
+    let type_factor = match throw_type {
+        ThrowType::Open => params.open_rate,
+        ThrowType::Break => params.break_rate,
+        ThrowType::Huck => params.huck_rate,
+    };
+
+    // ── Distance ────────────────────────────────────────────────────────────
+    let (dx, dy) = (x - tx, y - ty);
+    let range = params.range_yards * range_factor(&game_state.wind, dx, dy, &model.wind);
+    let distance_factor = logistic((range - distance) / params.range_spread_yards);
+
+    // ── Mark ────────────────────────────────────────────────────────────────
+    let obstruction = match thrower {
+        Some(_) => 1.0 - calculate_marking_difficulty_at(tx, ty, x, y, disc, mark),
+        None => 0.0,
+    };
+    let mark_factor = 1.0 - params.mark_block_rate * obstruction;
+
+    // ── Arrival ─────────────────────────────────────────────────────────────
+    let disc_speed =
+        params.disc_speed_yps * ground_speed_factor(&game_state.wind, dx, dy, &model.wind);
+    let flight = params.release_seconds + distance / disc_speed;
+    let receiver = nearest(players, x, y, |p| !p.is_defender && !p.has_disc);
+    let defender = nearest(players, x, y, |p| p.is_defender && !p.is_mark);
+
+    let receiver_factor = match receiver {
+        Some(d) => {
+            let arrival = d / params.player_speed_yps;
+            logistic(
+                (flight + params.receiver_grace_seconds - arrival) / params.time_spread_seconds,
+            )
+        }
+        None => 0.0,
+    };
+    let defender_factor = match (receiver, defender) {
+        (Some(r), Some(d)) => {
+            let receiver_arrival = r / params.player_speed_yps;
+            let defender_arrival = d / params.player_speed_yps;
+            let catch_time = flight.max(receiver_arrival);
+            // Can the defender be there at all, and how far ahead of the
+            // receiver?
+            let contested = logistic((catch_time - defender_arrival) / params.time_spread_seconds);
+            let ahead =
+                logistic((receiver_arrival - defender_arrival) / params.time_spread_seconds);
+            let block = params.contest_block_rate + (1.0 - params.contest_block_rate) * ahead;
+            1.0 - contested * block
+        }
+        _ => 1.0,
+    };
+
+    (type_factor * distance_factor * mark_factor * receiver_factor * defender_factor)
+        .clamp(0.0, 1.0)
+}
+
+/// Huck when long enough, break when the target lies inside the angle the
+/// mark takes away, open otherwise.
+pub fn classify_throw(
+    thrower: Option<&Player>,
+    x: f64,
+    y: f64,
+    distance: f64,
+    mark: &MarkParams,
+    params: &CompletionParams,
+) -> ThrowType {
+    if distance >= params.huck_distance_yards {
+        return ThrowType::Huck;
+    }
+    match thrower {
+        Some(t) if calculate_ease_at(t.x, t.y, x, y, mark) < 1.0 => ThrowType::Break,
+        _ => ThrowType::Open,
+    }
+}
+
+// ============================================================================
+// Layer builder and validation
+// ============================================================================
+
+/// Completion layer: `values[x][y]` in [0, 1].
+pub fn get_completion_layer(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    game_state: &GameState,
+    params: &HeatMapParams,
+) -> Vec<Vec<f64>> {
+    let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
+    for x in 0..num_cells_x {
+        for y in 0..num_cells_y {
+            let cx = x as f64 * grid_size + grid_size / 2.0;
+            let cy = y as f64 * grid_size + grid_size / 2.0;
+            values[x][y] = completion_probability(game_state, cx, cy, params);
+        }
+    }
+    values
+}
+
+/// Score the model's predictions for recorded throws against their outcomes.
+/// Returns `None` for an empty set.
+pub fn score(records: &[LabelledThrow], params: &HeatMapParams) -> Option<CompletionScore> {
+    if records.is_empty() {
+        return None;
+    }
+    // Keeps a confidently wrong prediction from giving an infinite loss.
+    const EPSILON: f64 = 1e-6;
+
+    let mut brier = 0.0_f64;
+    let mut log_loss = 0.0_f64;
+    let mut predicted = 0.0_f64;
+    let mut completed = 0usize;
+    for record in records {
+        let params = params.for_field(&record.game_state.field);
+        let p = completion_probability(
+            &record.game_state,
+            record.target_x,
+            record.target_y,
+            &params,
+        );
+        let outcome = if record.completed { 1.0 } else { 0.0 };
+        brier += (p - outcome).powi(2);
+        log_loss -= if record.completed {
+            p.max(EPSILON).ln()
+        } else {
+            (1.0 - p).max(EPSILON).ln()
+        };
+        predicted += p;
+        completed += usize::from(record.completed);
+    }
+
+    let n = records.len() as f64;
+    Some(CompletionScore {
+        throws: records.len(),
+        brier: brier / n,
+        log_loss: log_loss / n,
+        mean_predicted: predicted / n,
+        observed_rate: completed as f64 / n,
+    })
+}
+
+/// Distance from `(x, y)` to the nearest on-field player matching `filter`.
+fn nearest(players: &[Player], x: f64, y: f64, filter: impl Fn(&Player) -> bool) -> Option<f64> {
+    players
+        .iter()
+        .filter(|p| p.x >= SIDELINE_X_MIN && filter(p))
+        .map(|p| ((x - p.x).powi(2) + (y - p.y).powi(2)).sqrt())
+        .min_by(f64::total_cmp)
+}
+
+fn logistic(t: f64) -> f64 {
+    1.0 / (1.0 + (-t).exp())
+}
@@ -0,0 +1,198 @@
+//! Server configuration.
+//!
+//! Values are resolved in increasing order of precedence: built-in defaults,
+//! an optional TOML file (`--config` / `UFB_CONFIG`), environment variables,
+//! then command-line flags.  clap handles the last two, so this module only
+//! needs to load the file and lay the overrides on top.
+//!
+//! Example file:
+//!
+//! ```toml
+//! host = "127.0.0.1"
+//! port = 8080
+//! allowed_origins = ["http://localhost:5500"]
+//! default_grid_size = 0.5
+//! embedded_frontend = true
+//! layer_cache_capacity = 128
+//! log_format = "json"
+//!
+//! [model.mark]
+//! easyAngleRadians = 0.6
+//! ```
+//!
+//! Server settings are snake_case, like the matching CLI flags and
+//! environment variables.  The `[model.*]` tables keep the camelCase names of
+//! the heat-map parameters, so they can be copied to and from the JSON model
+//! file, the `defaultParams` of `GET /api/capabilities` and the rest of the
+//! JSON API unchanged; an unknown or snake_case parameter name is an error
+//! rather than silently ignored.
+//!
+//! `model_file` may instead point at a JSON parameter file (as written by the
+//! `calibrate` subcommand); it replaces the `[model]` table entirely.
+//! `epv_table` loads a CSV expected-possession-value table into the model.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::DEFAULT_LAYER_CACHE_CAPACITY;
+use crate::cli::ServerArgs;
+use crate::epv::EpvTable;
+use crate::frontend::FrontendSource;
+use crate::heatmap::{validate_grid_size, HeatMapParams};
+use crate::telemetry::LogFormat;
+
+const DEFAULT_HOST: &str = "0.0.0.0";
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_GRID_SIZE: f64 = 1.0;
+const DEFAULT_DATA_DIR: &str = "data";
+
+/// Origin entry that allows every origin (the historical behaviour, needed
+/// when the frontend is opened straight from the file system).
+pub const ANY_ORIGIN: &str = "*";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    /// Origins allowed by CORS; `["*"]` allows any.
+    pub allowed_origins: Vec<String>,
+    /// Grid size (yards) used when a request omits `gridSize`.
+    pub default_grid_size: f64,
+    /// Heat-map parameters used for every calculation.  Keys are camelCase,
+    /// as in the JSON model file.
+    pub model: HeatMapParams,
+    /// JSON file of heat-map parameters that replaces `model` when set.
+    pub model_file: Option<PathBuf>,
+    /// CSV table of `x,y,epv` loaded into `model.epv.table`.
+    pub epv_table: Option<PathBuf>,
+    /// Serve the web UI from this directory at `/`.
+    pub static_dir: Option<PathBuf>,
+    /// Serve the copy of the web UI embedded in the binary at `/` (ignored
+    /// when `static_dir` is set).
+    pub embedded_frontend: bool,
+    /// Directory holding server-side data (scenario library, training
+    /// examples, ...).
+    pub data_dir: PathBuf,
+    /// Heat-map layers kept in the in-process cache; 0 disables it.
+    pub layer_cache_capacity: usize,
+    /// Request log output: `text` or `json` (one object per line).
+    pub log_format: LogFormat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            allowed_origins: vec![ANY_ORIGIN.to_string()],
+            default_grid_size: DEFAULT_GRID_SIZE,
+            model: HeatMapParams::default(),
+            model_file: None,
+            epv_table: None,
+            static_dir: None,
+            embedded_frontend: false,
+            data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+            layer_cache_capacity: DEFAULT_LAYER_CACHE_CAPACITY,
+            log_format: LogFormat::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the file at `path` (or the defaults when `None`), apply the
+    /// env / CLI overrides and validate the result.
+    pub fn resolve(path: Option<&Path>, overrides: &ServerArgs) -> Result<Self, String> {
+        let mut config = match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|e| format!("cannot read config {}: {e}", path.display()))?;
+                toml::from_str(&text)
+                    .map_err(|e| format!("invalid config {}: {e}", path.display()))?
+            }
+            None => Config::default(),
+        };
+
+        if let Some(host) = &overrides.host {
+            config.host = host.clone();
+        }
+        if let Some(port) = overrides.port {
+            config.port = port;
+        }
+        if let Some(origins) = &overrides.allowed_origins {
+            config.allowed_origins = origins.clone();
+        }
+        if let Some(grid_size) = overrides.default_grid_size {
+            config.default_grid_size = grid_size;
+        }
+        if let Some(dir) = &overrides.static_dir {
+            config.static_dir = Some(dir.clone());
+        }
+        if overrides.embedded_frontend {
+            config.embedded_frontend = true;
+        }
+        if let Some(dir) = &overrides.data_dir {
+            config.data_dir = dir.clone();
+        }
+        if let Some(capacity) = overrides.layer_cache_capacity {
+            config.layer_cache_capacity = capacity;
+        }
+        if let Some(format) = overrides.log_format {
+            config.log_format = format;
+        }
+        if let Some(file) = &overrides.model_file {
+            config.model_file = Some(file.clone());
+        }
+        if let Some(file) = &config.model_file {
+            let text = fs::read_to_string(file)
+                .map_err(|e| format!("cannot read model file {}: {e}", file.display()))?;
+            config.model = serde_json::from_str(&text)
+                .map_err(|e| format!("invalid model file {}: {e}", file.display()))?;
+        }
+        if let Some(file) = &overrides.epv_table {
+            config.epv_table = Some(file.clone());
+        }
+        if let Some(file) = &config.epv_table {
+            let text = fs::read_to_string(file)
+                .map_err(|e| format!("cannot read EPV table {}: {e}", file.display()))?;
+            let table = EpvTable::from_csv(&text)
+                .map_err(|e| format!("invalid EPV table {}: {e}", file.display()))?;
+            config.model.epv.table = Some(table);
+        }
+
+        validate_grid_size(config.default_grid_size)
+            .map_err(|e| format!("default_grid_size {e}"))?;
+        Ok(config)
+    }
+
+    pub fn bind_addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == ANY_ORIGIN)
+    }
+
+    pub fn scenario_dir(&self) -> PathBuf {
+        self.data_dir.join("scenarios")
+    }
+
+    pub fn training_dir(&self) -> PathBuf {
+        self.data_dir.join("training")
+    }
+
+    pub fn recording_dir(&self) -> PathBuf {
+        self.data_dir.join("recordings")
+    }
+
+    /// Where the web UI is served from, or `None` for an API-only server.
+    pub fn frontend_source(&self) -> Option<FrontendSource> {
+        match &self.static_dir {
+            Some(dir) => Some(FrontendSource::Dir(dir.clone())),
+            None if self.embedded_frontend => Some(FrontendSource::Embedded),
+            None => None,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Expected-possession-value (EPV) layer.
+//!
+//! EPV is the probability that the offence eventually scores if it has the
+//! disc at a given spot.  It comes from a table fitted offline (CSV of
+//! `x,y,epv` on a regular grid, bilinearly interpolated) or, without one,
+//! from a simple ramp toward the scoring end zone.
+//!
+//! The expected-gain layer weighs a throw's upside against its downside:
+//!
+//! ```text
+//! gain = p · EPV(target) − (1 − p) · EPV_opponent(target)
+//! ```
+//!
+//! where `p` is the completion probability (see `completion`) and the
+//! opponent's EPV is the same surface seen from the other end, since a
+//! turnover hands them the disc roughly where it lands.
+
+// Grids are indexed by cell coordinates, as in `heatmap`.
+#![allow(clippy::needless_range_loop)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::completion::completion_probability;
+use crate::heatmap::HeatMapParams;
+use crate::models::{FieldDimensions, GameState};
+
+// ============================================================================
+// EPV CONSTANTS
+// Used only when no table is loaded.
+// ============================================================================
+
+/// EPV with the disc on the offence's own goal line.
+const EPV_OWN_GOAL_LINE_VALUE: f64 = 0.2;
+
+/// EPV with the disc on the scoring goal line.
+const EPV_SCORING_GOAL_LINE_VALUE: f64 = 0.85;
+
+/// A catch in the scoring end zone is a goal.
+const EPV_END_ZONE_VALUE: f64 = 1.0;
+
+// ============================================================================
+// Runtime parameters
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct EpvParams {
+    pub own_goal_line_value: f64,
+    pub scoring_goal_line_value: f64,
+    /// Fitted table; replaces the ramp above when present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<EpvTable>,
+}
+
+impl Default for EpvParams {
+    fn default() -> Self {
+        Self {
+            own_goal_line_value: EPV_OWN_GOAL_LINE_VALUE,
+            scoring_goal_line_value: EPV_SCORING_GOAL_LINE_VALUE,
+            table: None,
+        }
+    }
+}
+
+/// EPV sampled on a regular grid: `values[i][j]` is the EPV at
+/// `(xs[i], ys[j])`, in yards of the same frame as the game state (x = 0 is
+/// the back of the scoring end zone).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpvTable {
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    pub values: Vec<Vec<f64>>,
+}
+
+impl EpvTable {
+    /// Parse `x,y,epv` rows (an optional header line is skipped).  Every
+    /// `(x, y)` combination of the distinct xs and ys must be present once.
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut rows = Vec::new();
+        let mut first = true;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let header_allowed = std::mem::replace(&mut first, false);
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let parsed: Result<Vec<f64>, _> = fields.iter().map(|f| f.parse::<f64>()).collect();
+            match parsed {
+                Ok(v) if v.len() == 3 && v.iter().all(|x| x.is_finite()) => {
+                    rows.push((v[0], v[1], v[2]))
+                }
+                Err(_) if header_allowed => continue,
+                _ => return Err(format!("line {}: expected `x,y,epv`", n + 1)),
+            }
+        }
+
+        let mut xs: Vec<f64> = rows.iter().map(|r| r.0).collect();
+        let mut ys: Vec<f64> = rows.iter().map(|r| r.1).collect();
+        for axis in [&mut xs, &mut ys] {
+            axis.sort_by(f64::total_cmp);
+            axis.dedup();
+        }
+        if xs.len() < 2 || ys.len() < 2 {
+            return Err("table needs at least two distinct x and y values".to_string());
+        }
+        if rows.len() != xs.len() * ys.len() {
+            return Err(format!(
+                "table has {} rows but {} x values × {} y values",
+                rows.len(),
+                xs.len(),
+                ys.len()
+            ));
+        }
+
+        let mut values = vec![vec![f64::NAN; ys.len()]; xs.len()];
+        for (x, y, epv) in rows {
+            let i = xs.partition_point(|&v| v < x);
+            let j = ys.partition_point(|&v| v < y);
+            if !values[i][j].is_nan() {
+                return Err(format!("duplicate entry for ({x}, {y})"));
+            }
+            values[i][j] = epv;
+        }
+        Ok(Self { xs, ys, values })
+    }
+
+    /// Bilinear interpolation, clamped to the table's edges.
+    pub fn value_at(&self, x: f64, y: f64) -> f64 {
+        let (i, tx) = locate(&self.xs, x);
+        let (j, ty) = locate(&self.ys, y);
+        let v = &self.values;
+        let low = v[i][j] * (1.0 - ty) + v[i][j + 1] * ty;
+        let high = v[i + 1][j] * (1.0 - ty) + v[i + 1][j + 1] * ty;
+        low * (1.0 - tx) + high * tx
+    }
+}
+
+// ============================================================================
+// Per-cell helper functions
+// ============================================================================
+
+/// Offence's EPV with the disc at `(x, y)`.
+pub fn epv_at(x: f64, y: f64, field: &FieldDimensions, params: &EpvParams) -> f64 {
+    if let Some(table) = &params.table {
+        return table.value_at(x, y).clamp(0.0, 1.0);
+    }
+    if x < field.end_zone_depth {
+        return EPV_END_ZONE_VALUE;
+    }
+    // t: 0 at the scoring goal line, 1 at the own goal line.
+    let t = ((x - field.end_zone_depth) / field.field_length).clamp(0.0, 1.0);
+    params.scoring_goal_line_value
+        + (params.own_goal_line_value - params.scoring_goal_line_value) * t
+}
+
+/// Opponent's EPV after a turnover at `(x, y)`: the same surface mirrored
+/// end to end.  A turnover in either end zone is taken at its goal line.
+pub fn opponent_epv_at(x: f64, y: f64, field: &FieldDimensions, params: &EpvParams) -> f64 {
+    let x = x.clamp(
+        field.end_zone_depth,
+        field.total_length - field.end_zone_depth,
+    );
+    epv_at(field.total_length - x, y, field, params)
+}
+
+// ============================================================================
+// Layer builders
+// ============================================================================
+
+/// EPV layer: `values[x][y]` in [0, 1].
+pub fn get_epv_layer(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    field: &FieldDimensions,
+    params: &EpvParams,
+) -> Vec<Vec<f64>> {
+    let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
+    for x in 0..num_cells_x {
+        for y in 0..num_cells_y {
+            let cx = x as f64 * grid_size + grid_size / 2.0;
+            let cy = y as f64 * grid_size + grid_size / 2.0;
+            values[x][y] = epv_at(cx, cy, field, params);
+        }
+    }
+    values
+}
+
+/// Expected-gain layer: `values[x][y]` in [−1, 1].  Being signed and already
+/// weighted by the completion probability, it is not multiplied with the
+/// [0, 1] layers: a zero from coverage would outrank every negative cell and
+/// combining it with completion would count `p` twice.  `calculate_heat_map`
+/// therefore shows it on its own.
+pub fn get_expected_gain_layer(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    game_state: &GameState,
+    params: &HeatMapParams,
+) -> Vec<Vec<f64>> {
+    let field = &game_state.field;
+    let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
+    for x in 0..num_cells_x {
+        for y in 0..num_cells_y {
+            let cx = x as f64 * grid_size + grid_size / 2.0;
+            let cy = y as f64 * grid_size + grid_size / 2.0;
+            let p = completion_probability(game_state, cx, cy, params);
+            values[x][y] = p * epv_at(cx, cy, field, &params.epv)
+                - (1.0 - p) * opponent_epv_at(cx, cy, field, &params.epv);
+        }
+    }
+    values
+}
+
+/// Index of the lower grid line around `v` and the fraction toward the next
+/// one, clamped to the axis.
+fn locate(axis: &[f64], v: f64) -> (usize, f64) {
+    let i = axis.partition_point(|&a| a <= v).clamp(1, axis.len() - 1) - 1;
+    let t = ((v - axis[i]) / (axis[i + 1] - axis[i])).clamp(0.0, 1.0);
+    (i, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::LayerCache;
+    use crate::field::FieldPreset;
+    use crate::heatmap::{calculate_heat_map, HeatMapParams};
+    use crate::models::{Disc, GameState, HeatMapModes, Player, Wind};
+
+    fn player(id: &str, x: f64, y: f64, defender: bool) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: false,
+            label: Some("1".to_string()),
+        }
+    }
+
+    #[test]
+    fn expected_gain_is_never_combined() {
+        let gs = GameState {
+            players: vec![
+                player("t", 70.0, 20.0, false),
+                player("o", 50.0, 15.0, false),
+                player("d", 49.0, 16.0, true),
+            ],
+            disc: Disc {
+                x: 70.0,
+                y: 20.0,
+                holder_id: Some("t".to_string()),
+            },
+            field: FieldPreset::Usa.dimensions(),
+            stall: 0,
+            wind: Wind::default(),
+        };
+        let modes = |others: bool| HeatMapModes {
+            catch: others,
+            difficulty: others,
+            marking_difficulty: others,
+            coverage: others,
+            completion: others,
+            epv: others,
+            expected_gain: true,
+            reset_space: others,
+        };
+        let params = HeatMapParams::default();
+        let cache = LayerCache::disabled();
+        let alone = calculate_heat_map(&gs, &modes(false), false, 2.0, &params, &cache).unwrap();
+        let with_all = calculate_heat_map(&gs, &modes(true), false, 2.0, &params, &cache).unwrap();
+        assert_eq!(with_all.mode, "expectedGain");
+        assert_eq!(with_all.values, alone.values);
+        assert!(alone.values.iter().flatten().any(|&v| v < 0.0));
+    }
+}
@@ -0,0 +1,361 @@
+//! Heat-map export formats.
+//!
+//! Cell `(i, j)` of a `HeatMapData` covers `[i·g, (i+1)·g] × [j·g, (j+1)·g]`
+//! in field yards, `g` being the grid size, so every format states positions
+//! in the same frame as the game state (x = 0 at the back of the scoring end
+//! zone).
+//!
+//!   * **CSV** — long form, one `x,y,value` row per cell at the cell centre;
+//!   * **NPY** — NumPy `.npy` (format 1.0) of little-endian `f64`, shape
+//!     `(nx, ny)`, so `array[i, j] == values[i][j]`;
+//!   * **GeoJSON** — a `FeatureCollection` with one square `Polygon` per cell
+//!     carrying its `value`.  Coordinates are planar field yards, not
+//!     longitude / latitude.
+//!
+//! For the live UI there is also a compact binary transport, all
+//! little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     4  magic "UFBH"
+//!      4     1  version (1)
+//!      5     1  encoding: 1 = f32, 2 = u8
+//!      6     2  length of `mode` in bytes (u16)
+//!      8     4  nx (u32)
+//!     12     4  ny (u32)
+//!     16     4  gridSize (f32)
+//!     20     4  throwerX (f32)
+//!     24     4  throwerY (f32)
+//!     28     4  min value (f32)
+//!     32     4  max value (f32)
+//!     36     …  mode (UTF-8), zero-padded to a multiple of 4 bytes
+//!      …     …  nx · ny values, x-major (`values[i][j]` at `i · ny + j`)
+//! ```
+//!
+//! `f32` values are stored as is.  `u8` values are quantised between min and
+//! max, `v ≈ min + q / 255 · (max − min)`, a quarter of the size and exact
+//! enough for a map the UI draws with 8-bit colours.  Values start on a
+//! 4-byte boundary so a `Float32Array` can view them without copying.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::HeatMapData;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// The `HeatMapData` JSON itself.
+    #[default]
+    Json,
+    Csv,
+    Npy,
+    #[serde(alias = "geojson")]
+    GeoJson,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Npy => "application/octet-stream",
+            ExportFormat::GeoJson => "application/geo+json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Npy => "npy",
+            ExportFormat::GeoJson => "geojson",
+        }
+    }
+}
+
+/// `data` encoded as `format`.
+pub fn export(data: &HeatMapData, format: ExportFormat) -> Vec<u8> {
+    match format {
+        ExportFormat::Json => serde_json::to_vec(data).unwrap_or_default(),
+        ExportFormat::Csv => to_csv(data).into_bytes(),
+        ExportFormat::Npy => to_npy(data),
+        ExportFormat::GeoJson => serde_json::to_vec(&to_geojson(data)).unwrap_or_default(),
+    }
+}
+
+/// Long-form CSV: a header, then `x,y,value` per cell centre, x-major.
+pub fn to_csv(data: &HeatMapData) -> String {
+    let g = data.grid_size;
+    let mut out = String::from("x,y,value\n");
+    for (i, column) in data.values.iter().enumerate() {
+        for (j, value) in column.iter().enumerate() {
+            let x = (i as f64 + 0.5) * g;
+            let y = (j as f64 + 0.5) * g;
+            out.push_str(&format!("{x},{y},{value}\n"));
+        }
+    }
+    out
+}
+
+/// NumPy `.npy` bytes of the grid.  Short inner rows are padded with NaN so
+/// the array is always rectangular.
+pub fn to_npy(data: &HeatMapData) -> Vec<u8> {
+    let nx = data.values.len();
+    let ny = data.values.iter().map(Vec::len).max().unwrap_or(0);
+
+    // The header is padded with spaces so the data starts on a 64-byte
+    // boundary, and ends with a newline.
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({nx}, {ny}), }}");
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(MAGIC.len() + 2 + header.len() + nx * ny * 8);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for column in &data.values {
+        for j in 0..ny {
+            let value = column.get(j).copied().unwrap_or(f64::NAN);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// GeoJSON `FeatureCollection` of cell squares.  The collection also carries
+/// the heat map's `gridSize`, `mode` and thrower position.
+pub fn to_geojson(data: &HeatMapData) -> Value {
+    let g = data.grid_size;
+    let mut features = Vec::new();
+    for (i, column) in data.values.iter().enumerate() {
+        for (j, &value) in column.iter().enumerate() {
+            let (x0, y0) = (i as f64 * g, j as f64 * g);
+            let (x1, y1) = (x0 + g, y0 + g);
+            features.push(json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]]],
+                },
+                "properties": { "i": i, "j": j, "value": value },
+            }));
+        }
+    }
+    json!({
+        "type": "FeatureCollection",
+        "gridSize": g,
+        "mode": data.mode,
+        "throwerX": data.thrower_x,
+        "throwerY": data.thrower_y,
+        "features": features,
+    })
+}
+
+// ============================================================================
+// Binary transport
+// ============================================================================
+
+const BINARY_MAGIC: &[u8; 4] = b"UFBH";
+const BINARY_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    F32,
+    U8,
+}
+
+impl BinaryEncoding {
+    pub const ALL: [BinaryEncoding; 2] = [BinaryEncoding::F32, BinaryEncoding::U8];
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            BinaryEncoding::F32 => "application/vnd.ufb.heatmap.f32",
+            BinaryEncoding::U8 => "application/vnd.ufb.heatmap.u8",
+        }
+    }
+
+    /// The first binary encoding named in an `Accept` header, if any.
+    /// Quality values are ignored; list the preferred type first.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept.split(',').find_map(|range| {
+            let media_type = range.split(';').next().unwrap_or("").trim();
+            Self::ALL
+                .into_iter()
+                .find(|e| e.media_type().eq_ignore_ascii_case(media_type))
+        })
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            BinaryEncoding::F32 => 1,
+            BinaryEncoding::U8 => 2,
+        }
+    }
+}
+
+/// `data` in the binary transport described above.  Short inner rows are
+/// padded with NaN (f32) or 0 (u8).
+pub fn to_binary(data: &HeatMapData, encoding: BinaryEncoding) -> Vec<u8> {
+    let nx = data.values.len();
+    let ny = data.values.iter().map(Vec::len).max().unwrap_or(0);
+    let (min, max) = data
+        .values
+        .iter()
+        .flatten()
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let (min, max) = if min <= max { (min, max) } else { (0.0, 0.0) };
+    let mode = data.mode.as_bytes();
+    let mode_len = mode.len().min(u16::MAX as usize);
+
+    let value_size = match encoding {
+        BinaryEncoding::F32 => 4,
+        BinaryEncoding::U8 => 1,
+    };
+    let mut out = Vec::with_capacity(40 + mode_len + nx * ny * value_size);
+    out.extend_from_slice(BINARY_MAGIC);
+    out.push(BINARY_VERSION);
+    out.push(encoding.code());
+    out.extend_from_slice(&(mode_len as u16).to_le_bytes());
+    out.extend_from_slice(&(nx as u32).to_le_bytes());
+    out.extend_from_slice(&(ny as u32).to_le_bytes());
+    for value in [data.grid_size, data.thrower_x, data.thrower_y, min, max] {
+        out.extend_from_slice(&(value as f32).to_le_bytes());
+    }
+    out.extend_from_slice(&mode[..mode_len]);
+    out.resize(out.len().next_multiple_of(4), 0);
+
+    let range = max - min;
+    for column in &data.values {
+        for j in 0..ny {
+            let value = column.get(j).copied().unwrap_or(f64::NAN);
+            match encoding {
+                BinaryEncoding::F32 => out.extend_from_slice(&(value as f32).to_le_bytes()),
+                BinaryEncoding::U8 => {
+                    let q = if range > 0.0 && value.is_finite() {
+                        ((value - min) / range * 255.0).round() as u8
+                    } else {
+                        0
+                    };
+                    out.push(q);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> HeatMapData {
+        HeatMapData {
+            grid_size: 2.0,
+            // The second column is short, to exercise the padding.
+            values: vec![vec![0.0, 0.25, 1.0], vec![0.5, 0.75], vec![1.5, 2.0, 0.125]],
+            thrower_x: 40.0,
+            thrower_y: 20.0,
+            mode: "catch".to_string(),
+        }
+    }
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn f32_at(bytes: &[u8], at: usize) -> f32 {
+        f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn npy_round_trips() {
+        let data = data();
+        let bytes = to_npy(&data);
+
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16_at(&bytes, 8) as usize;
+        let start = 10 + header_len;
+        assert_eq!(start % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..start]).unwrap();
+        assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': (3, 3), }"));
+        assert!(header.ends_with('\n'));
+
+        let values: Vec<f64> = bytes[start..]
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(values.len(), 9);
+        for (i, column) in data.values.iter().enumerate() {
+            for j in 0..3 {
+                match column.get(j) {
+                    Some(&v) => assert_eq!(values[i * 3 + j], v),
+                    None => assert!(values[i * 3 + j].is_nan()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn binary_f32_round_trips() {
+        let data = data();
+        let bytes = to_binary(&data, BinaryEncoding::F32);
+
+        assert_eq!(&bytes[..4], BINARY_MAGIC);
+        assert_eq!(bytes[4], BINARY_VERSION);
+        assert_eq!(bytes[5], 1);
+        let mode_len = u16_at(&bytes, 6) as usize;
+        let (nx, ny) = (u32_at(&bytes, 8) as usize, u32_at(&bytes, 12) as usize);
+        assert_eq!((nx, ny), (3, 3));
+        assert_eq!(f32_at(&bytes, 16), 2.0);
+        assert_eq!(f32_at(&bytes, 20), 40.0);
+        assert_eq!(f32_at(&bytes, 24), 20.0);
+        assert_eq!(f32_at(&bytes, 28), 0.0);
+        assert_eq!(f32_at(&bytes, 32), 2.0);
+        assert_eq!(&bytes[36..36 + mode_len], b"catch");
+
+        let start = (36 + mode_len).next_multiple_of(4);
+        assert_eq!(bytes.len(), start + nx * ny * 4);
+        for (i, column) in data.values.iter().enumerate() {
+            for j in 0..ny {
+                let value = f32_at(&bytes, start + (i * ny + j) * 4);
+                match column.get(j) {
+                    Some(&v) => assert_eq!(value, v as f32),
+                    None => assert!(value.is_nan()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn binary_u8_round_trips_within_a_quantisation_step() {
+        let data = data();
+        let bytes = to_binary(&data, BinaryEncoding::U8);
+
+        assert_eq!(bytes[5], 2);
+        let mode_len = u16_at(&bytes, 6) as usize;
+        let ny = u32_at(&bytes, 12) as usize;
+        let (min, max) = (f32_at(&bytes, 28) as f64, f32_at(&bytes, 32) as f64);
+        let start = (36 + mode_len).next_multiple_of(4);
+        assert_eq!(bytes.len(), start + 3 * ny);
+
+        let step = (max - min) / 255.0;
+        for (i, column) in data.values.iter().enumerate() {
+            for (j, &v) in column.iter().enumerate() {
+                let decoded = min + bytes[start + i * ny + j] as f64 * step;
+                assert!((decoded - v).abs() <= step / 2.0);
+            }
+        }
+        // Padding decodes to the minimum.
+        assert_eq!(bytes[start + ny + 2], 0);
+    }
+}
@@ -0,0 +1,381 @@
+//! Named field presets and length units.
+//!
+//! Everything inside the server is in yards on the USA Ultimate field's
+//! frame (x = 0 at the back of the scoring end zone).  A request's `field`
+//! may be either a preset name (`"field": "wfdf"`) or explicit dimensions,
+//! optionally in metres (`"units": "metres"`); both are converted to yards
+//! when the request is parsed.  `units` only applies to those dimensions:
+//! player, disc and target coordinates, and every position the server
+//! returns, are always in yards, whatever the field.
+//!
+//! Model parameters are stated for the reference field and stretched to the
+//! request's field by `HeatMapParams::for_field`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::FieldDimensions;
+
+// ============================================================================
+// FIELD CONSTANTS
+// ============================================================================
+
+/// Exact, by definition of the international yard.
+pub const METRES_PER_YARD: f64 = 0.9144;
+
+/// Playing-field length (yards) of the reference field the model parameters
+/// are tuned for.
+const REFERENCE_FIELD_LENGTH_YARDS: f64 = 70.0;
+
+/// Width (yards) of the reference field.
+const REFERENCE_FIELD_WIDTH_YARDS: f64 = 40.0;
+
+/// Largest total length or width (yards) a request may ask for.  With the
+/// smallest grid size this bounds a heat map at 800 × 800 cells.
+pub const MAX_FIELD_DIMENSION_YARDS: f64 = 200.0;
+
+// ============================================================================
+// Units
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LengthUnit {
+    #[default]
+    Yards,
+    #[serde(alias = "meters")]
+    Metres,
+}
+
+impl LengthUnit {
+    pub fn to_yards(self, value: f64) -> f64 {
+        match self {
+            LengthUnit::Yards => value,
+            LengthUnit::Metres => value / METRES_PER_YARD,
+        }
+    }
+}
+
+// ============================================================================
+// Presets
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldPreset {
+    /// USA Ultimate: 70 × 40 yards, 20-yard end zones.
+    Usa,
+    /// WFDF: 64 × 37 metres, 18-metre end zones.
+    Wfdf,
+    /// Beach: 75 × 25 metres overall, 15-metre end zones (a 45-metre
+    /// playing field).
+    Beach,
+    /// Indoor / small-sided: 40 × 20 yards, 10-yard end zones.
+    Indoor,
+}
+
+impl FieldPreset {
+    pub const ALL: [FieldPreset; 4] = [
+        FieldPreset::Usa,
+        FieldPreset::Wfdf,
+        FieldPreset::Beach,
+        FieldPreset::Indoor,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldPreset::Usa => "usa",
+            FieldPreset::Wfdf => "wfdf",
+            FieldPreset::Beach => "beach",
+            FieldPreset::Indoor => "indoor",
+        }
+    }
+
+    /// Case-insensitive lookup by `name()`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Units the preset's rules are written in.
+    pub fn units(self) -> LengthUnit {
+        match self {
+            FieldPreset::Usa | FieldPreset::Indoor => LengthUnit::Yards,
+            FieldPreset::Wfdf | FieldPreset::Beach => LengthUnit::Metres,
+        }
+    }
+
+    /// `(playing length, width, end-zone depth)` in `units()`.
+    pub fn native_dimensions(self) -> (f64, f64, f64) {
+        match self {
+            FieldPreset::Usa => (70.0, 40.0, 20.0),
+            FieldPreset::Wfdf => (64.0, 37.0, 18.0),
+            FieldPreset::Beach => (45.0, 25.0, 15.0),
+            FieldPreset::Indoor => (40.0, 20.0, 10.0),
+        }
+    }
+
+    /// The preset in yards.
+    pub fn dimensions(self) -> FieldDimensions {
+        let units = self.units();
+        let (length, width, end_zone) = self.native_dimensions();
+        FieldDimensions::new(
+            units.to_yards(length),
+            units.to_yards(width),
+            units.to_yards(end_zone),
+        )
+    }
+}
+
+impl FieldDimensions {
+    /// Dimensions in yards; `total_length` is derived.
+    pub fn new(field_length: f64, field_width: f64, end_zone_depth: f64) -> Self {
+        Self {
+            field_length,
+            field_width,
+            end_zone_depth,
+            total_length: field_length + 2.0 * end_zone_depth,
+        }
+    }
+
+    /// Playing-field length relative to the reference field.
+    pub fn length_scale(&self) -> f64 {
+        self.field_length / REFERENCE_FIELD_LENGTH_YARDS
+    }
+
+    /// Width relative to the reference field.
+    pub fn width_scale(&self) -> f64 {
+        self.field_width / REFERENCE_FIELD_WIDTH_YARDS
+    }
+}
+
+// ============================================================================
+// Request parsing
+// ============================================================================
+
+/// What a request may send as `field`.  Parsed through a `Value` rather
+/// than `#[serde(untagged)]` so a misspelt or missing key is named in the
+/// error instead of "did not match any variant".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
+pub enum FieldSpec {
+    Preset(String),
+    Dimensions(DimensionsSpec),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DimensionsSpec {
+    field_length: f64,
+    field_width: f64,
+    end_zone_depth: f64,
+    /// Derived from the other three when omitted.
+    #[serde(default)]
+    total_length: Option<f64>,
+    #[serde(default)]
+    units: LengthUnit,
+}
+
+impl TryFrom<serde_json::Value> for FieldSpec {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, String> {
+        match value {
+            serde_json::Value::String(name) => Ok(FieldSpec::Preset(name)),
+            value @ serde_json::Value::Object(_) => serde_json::from_value(value)
+                .map(FieldSpec::Dimensions)
+                .map_err(|e| format!("field dimensions: {e}")),
+            other => Err(format!(
+                "field must be a preset name or an object of dimensions, got {other}"
+            )),
+        }
+    }
+}
+
+impl TryFrom<FieldSpec> for FieldDimensions {
+    type Error = String;
+
+    fn try_from(spec: FieldSpec) -> Result<Self, String> {
+        match spec {
+            FieldSpec::Preset(name) => FieldPreset::from_name(&name)
+                .map(FieldPreset::dimensions)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = FieldPreset::ALL.iter().map(|p| p.name()).collect();
+                    format!(
+                        "unknown field preset `{name}` (expected one of: {})",
+                        names.join(", ")
+                    )
+                }),
+            FieldSpec::Dimensions(d) => {
+                let u = d.units;
+                let mut field = FieldDimensions::new(
+                    u.to_yards(d.field_length),
+                    u.to_yards(d.field_width),
+                    u.to_yards(d.end_zone_depth),
+                );
+                if let Some(total) = d.total_length {
+                    field.total_length = u.to_yards(total);
+                }
+                let all = [
+                    field.field_length,
+                    field.field_width,
+                    field.end_zone_depth,
+                    field.total_length,
+                ];
+                if all.iter().any(|v| !v.is_finite() || *v < 0.0)
+                    || field.field_length == 0.0
+                    || field.field_width == 0.0
+                {
+                    return Err("field dimensions must be finite, positive numbers".to_string());
+                }
+                let min_total = field.field_length + 2.0 * field.end_zone_depth;
+                // Both sides went through the same unit conversion; allow for
+                // its rounding.
+                if field.total_length < min_total - 1e-9 {
+                    return Err(format!(
+                        "totalLength must be at least fieldLength + 2 × endZoneDepth \
+                         ({min_total} yards), got {} yards",
+                        field.total_length
+                    ));
+                }
+                if field.total_length > MAX_FIELD_DIMENSION_YARDS
+                    || field.field_width > MAX_FIELD_DIMENSION_YARDS
+                {
+                    return Err(format!(
+                        "field length and width must be at most {MAX_FIELD_DIMENSION_YARDS} \
+                         yards, got {} × {} yards",
+                        field.total_length, field.field_width
+                    ));
+                }
+                Ok(field)
+            }
+        }
+    }
+}
+
+/// One entry of `GET /api/field-presets`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldPresetInfo {
+    pub name: &'static str,
+    pub units: LengthUnit,
+    /// Playing length, width and end-zone depth in `units`.
+    pub field_length: f64,
+    pub field_width: f64,
+    pub end_zone_depth: f64,
+    /// The same field in yards, as the server uses it.
+    pub dimensions: FieldDimensions,
+    /// Units of player, disc and target coordinates on this field: always
+    /// yards, even when the preset is defined in metres.
+    pub coordinate_units: LengthUnit,
+}
+
+impl From<FieldPreset> for FieldPresetInfo {
+    fn from(preset: FieldPreset) -> Self {
+        let (field_length, field_width, end_zone_depth) = preset.native_dimensions();
+        Self {
+            name: preset.name(),
+            units: preset.units(),
+            field_length,
+            field_width,
+            end_zone_depth,
+            dimensions: preset.dimensions(),
+            coordinate_units: LengthUnit::Yards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spec: serde_json::Value) -> Result<FieldDimensions, String> {
+        let spec: FieldSpec = serde_json::from_value(spec).map_err(|e| e.to_string())?;
+        FieldDimensions::try_from(spec)
+    }
+
+    #[test]
+    fn metres_are_converted_to_yards() {
+        let field = parse(serde_json::json!({
+            "fieldLength": 64.0,
+            "fieldWidth": 37.0,
+            "endZoneDepth": 18.0,
+            "units": "metres",
+        }))
+        .unwrap();
+        assert_eq!(field, FieldPreset::Wfdf.dimensions());
+        assert!((field.field_length - 64.0 / METRES_PER_YARD).abs() < 1e-9);
+        assert!((field.total_length - 100.0 / METRES_PER_YARD).abs() < 1e-9);
+    }
+
+    #[test]
+    fn explicit_total_length_is_converted_and_checked() {
+        let field = parse(serde_json::json!({
+            "fieldLength": 64.0,
+            "fieldWidth": 37.0,
+            "endZoneDepth": 18.0,
+            "totalLength": 110.0,
+            "units": "meters",
+        }))
+        .unwrap();
+        assert!((field.total_length - 110.0 / METRES_PER_YARD).abs() < 1e-9);
+
+        assert!(parse(serde_json::json!({
+            "fieldLength": 64.0,
+            "fieldWidth": 37.0,
+            "endZoneDepth": 18.0,
+            "totalLength": 90.0,
+            "units": "metres",
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn oversized_fields_are_rejected() {
+        assert!(parse(serde_json::json!({
+            "fieldLength": 190.0,
+            "fieldWidth": 40.0,
+            "endZoneDepth": 20.0,
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn unknown_and_missing_keys_are_named() {
+        let err = parse(serde_json::json!({
+            "fieldLenght": 70.0,
+            "fieldWidth": 40.0,
+            "endZoneDepth": 20.0,
+        }))
+        .unwrap_err();
+        assert!(err.contains("unknown field `fieldLenght`"), "{err}");
+
+        let err = parse(serde_json::json!({
+            "fieldLength": 70.0,
+            "fieldWidth": 40.0,
+        }))
+        .unwrap_err();
+        assert!(err.contains("missing field `endZoneDepth`"), "{err}");
+
+        let err = parse(serde_json::json!(70.0)).unwrap_err();
+        assert!(err.contains("preset name or an object"), "{err}");
+    }
+
+    #[test]
+    fn serialized_dimensions_parse_back() {
+        let field = FieldPreset::Wfdf.dimensions();
+        assert_eq!(parse(serde_json::to_value(&field).unwrap()).unwrap(), field);
+        assert_eq!(
+            parse(serde_json::json!("beach")).unwrap(),
+            FieldPreset::Beach.dimensions()
+        );
+    }
+
+    #[test]
+    fn preset_info_states_yard_coordinates() {
+        let info = FieldPresetInfo::from(FieldPreset::Beach);
+        assert_eq!(info.units, LengthUnit::Metres);
+        assert_eq!(info.coordinate_units, LengthUnit::Yards);
+        assert_eq!(info.dimensions, FieldPreset::Beach.dimensions());
+    }
+}
@@ -0,0 +1,85 @@
+//! Static frontend served at `/`.
+//!
+//! Only the files that make up the web UI are served — never an arbitrary
+//! path — either read from a directory at request time (handy while editing
+//! the JavaScript) or from copies embedded into the binary at compile time.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+
+/// Frontend files: name, content type, embedded copy.
+const FILES: [(&str, &str, &str); 5] = [
+    (
+        "index.html",
+        "text/html; charset=utf-8",
+        include_str!("../../index.html"),
+    ),
+    (
+        "app.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../app.js"),
+    ),
+    (
+        "field.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../field.js"),
+    ),
+    (
+        "game.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../game.js"),
+    ),
+    (
+        "styles.css",
+        "text/css; charset=utf-8",
+        include_str!("../../styles.css"),
+    ),
+];
+
+/// Where the frontend files come from.
+#[derive(Debug, Clone)]
+pub enum FrontendSource {
+    /// Read from this directory on every request.
+    Dir(PathBuf),
+    /// Use the copies compiled into the binary.
+    Embedded,
+}
+
+/// Routes for `/` and the individual frontend files.
+pub fn router(source: FrontendSource) -> Router {
+    Router::new()
+        .route("/", get(index_handler))
+        .route("/:file", get(file_handler))
+        .with_state(Arc::new(source))
+}
+
+async fn index_handler(State(source): State<Arc<FrontendSource>>) -> Response {
+    serve(&source, "index.html").await
+}
+
+async fn file_handler(
+    State(source): State<Arc<FrontendSource>>,
+    Path(file): Path<String>,
+) -> Response {
+    serve(&source, &file).await
+}
+
+async fn serve(source: &FrontendSource, name: &str) -> Response {
+    let Some(&(name, content_type, embedded)) = FILES.iter().find(|(n, _, _)| *n == name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let body = match source {
+        FrontendSource::Embedded => embedded.to_string(),
+        FrontendSource::Dir(dir) => match tokio::fs::read_to_string(dir.join(name)).await {
+            Ok(body) => body,
+            Err(_) => return StatusCode::NOT_FOUND.into_response(),
+        },
+    };
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
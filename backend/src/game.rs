@@ -4,28 +4,69 @@
 
 use rand::Rng;
 
-use crate::heatmap::{
-    combined_heat_map_sum, get_catch_layer, get_coverage_layer, get_difficulty_layer,
-    get_marking_difficulty_layer,
-};
-use crate::models::GameState;
+use crate::cache::LayerCache;
+use crate::heatmap::{combined_heat_map_sum, get_coverage_layer, HeatMapParams};
+use crate::knn::{features_for, predict_offset, DefenderSample};
+use crate::models::{DefenderSearch, GameState, SearchShape};
+use crate::optimize::nelder_mead;
 
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
 
-/// Maximum search radius (yards) around the offender when positioning the
-/// downfield defender optimally.
+/// Search radius (yards) around the offender when positioning the downfield
+/// defender and the request does not give one.
 const DEFENDER_SEARCH_RADIUS_YARDS: f64 = 5.0;
 
+/// Largest search radius a request may ask for: every candidate cell costs a
+/// full heat-map evaluation.
+pub const DEFENDER_SEARCH_MAX_RADIUS_YARDS: f64 = 15.0;
+
+/// Defender top speed (yards per second) for the reach limit when the
+/// request does not give one; the completion model's player speed.
+const DEFENDER_MAX_SPEED_YPS: f64 = 7.0;
+
+/// Defender acceleration (yards per second²) from rest for the reach limit
+/// when the request does not give one.
+const DEFENDER_ACCELERATION_YPS2: f64 = 5.0;
+
+/// Longest `reactionSeconds` a request may ask for.
+const DEFENDER_MAX_REACTION_SECONDS: f64 = 10.0;
+
+/// Alternating projections used to find a point in the intersection of the
+/// search region and the reach disc.
+const DEFENDER_REACH_PROJECTION_ITERATIONS: usize = 32;
+
+/// Iteration cap for the continuous refinement after the grid pass.
+const DEFENDER_REFINE_MAX_ITERATIONS: usize = 40;
+
+/// Refinement stops once the simplex's costs agree to within this.
+const DEFENDER_REFINE_TOLERANCE: f64 = 1e-6;
+
+/// Cost added per yard a refinement step strays outside the search region,
+/// so the simplex is pulled back in.
+const DEFENDER_REFINE_PENALTY_PER_YARD: f64 = 1e3;
+
+/// How far downfield (yards, lower x) of the disc `position_offender_stack`
+/// sets up on the reference field; scaled with the field's length.
+const STACK_OFFSET_YARDS: f64 = 20.0;
+
+/// Blend weight used by `position_defender_hybrid` when the request does not
+/// specify one (equal say for the heat map and the learned model).
+pub const HYBRID_DEFAULT_BLEND_WEIGHT: f64 = 0.5;
+
+/// `(x, y, combined heat-map sum)` for one candidate defender position.
+type Candidate = (f64, f64, f64);
+
 // ---------------------------------------------------------------------------
 // AI positioning
 // ---------------------------------------------------------------------------
 
-/// Move the defender with the given label to the field cell (within
-/// `DEFENDER_SEARCH_RADIUS_YARDS` of the offender with the same label) that
-/// minimises the pre-normalised combined heat-map sum.  All other defenders
-/// remain in place, so their coverage is included when evaluating positions.
+/// Move the defender with the given label to the field cell inside the
+/// `search` region around the offender with the same label that minimises
+/// the pre-normalised combined heat-map sum.  All other defenders remain in
+/// place, so their coverage is included when evaluating positions.  With
+/// `search.refine` the best cell is then refined continuously.
 ///
 /// Returns the new `(x, y)` position, or `None` when there is no defender or
 /// offender with that label.
@@ -33,7 +74,133 @@ pub fn position_defender_optimal(
     gs: &mut GameState,
     grid_size: f64,
     defender_label: &str,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    search: &DefenderSearch,
 ) -> Option<(f64, f64)> {
+    let (defender_idx, region, candidates) =
+        score_defender_candidates(gs, grid_size, defender_label, params, cache, search)?;
+
+    let mut best_sum = f64::INFINITY;
+    let mut best_x = gs.players[defender_idx].x;
+    let mut best_y = gs.players[defender_idx].y;
+    for &(x, y, s) in &candidates {
+        if s < best_sum {
+            best_sum = s;
+            best_x = x;
+            best_y = y;
+        }
+    }
+    if search.refine && !candidates.is_empty() {
+        (best_x, best_y) = refine_position(
+            gs,
+            defender_idx,
+            &region,
+            grid_size,
+            (best_x, best_y),
+            |gs| combined_heat_map_sum(gs, grid_size, params, cache),
+        );
+    }
+
+    gs.players[defender_idx].x = best_x;
+    gs.players[defender_idx].y = best_y;
+    Some((best_x, best_y))
+}
+
+/// Move the defender with the given label to the candidate cell (same search
+/// region as `position_defender_optimal`) that minimises a blend of
+///
+///   * the combined heat-map sum, min-max normalised over the candidates, and
+///   * the squared distance to the k-NN prediction, divided by the squared
+///     search radius,
+///
+/// weighted `1 − blend_weight` and `blend_weight` respectively.  A weight of 0
+/// is the heat-map optimum; 1 is the candidate nearest the coaches' intent.
+/// Without enough training samples the learned term is dropped and the result
+/// equals `position_defender_optimal`.
+///
+/// Returns the new `(x, y)` position, or `None` when there is no defender or
+/// offender with that label.
+#[allow(clippy::too_many_arguments)]
+pub fn position_defender_hybrid(
+    gs: &mut GameState,
+    grid_size: f64,
+    defender_label: &str,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    search: &DefenderSearch,
+    samples: &[DefenderSample],
+    k: usize,
+    blend_weight: f64,
+) -> Option<(f64, f64)> {
+    let Some((target_x, target_y)) = learned_defender_target(gs, defender_label, samples, k) else {
+        return position_defender_optimal(gs, grid_size, defender_label, params, cache, search);
+    };
+    let (defender_idx, region, candidates) =
+        score_defender_candidates(gs, grid_size, defender_label, params, cache, search)?;
+
+    let w = blend_weight.clamp(0.0, 1.0);
+    let (min_sum, max_sum) = candidates.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(lo, hi), &(_, _, s)| (lo.min(s), hi.max(s)),
+    );
+    let range = max_sum - min_sum;
+    let radius = search.radius_yards.unwrap_or(DEFENDER_SEARCH_RADIUS_YARDS);
+    let r2 = radius * radius;
+    let cost = |x: f64, y: f64, s: f64| {
+        let heat = if range > 0.0 {
+            (s - min_sum) / range
+        } else {
+            0.0
+        };
+        let dist2 = ((x - target_x).powi(2) + (y - target_y).powi(2)) / r2;
+        (1.0 - w) * heat + w * dist2
+    };
+
+    let mut best_cost = f64::INFINITY;
+    let mut best_x = gs.players[defender_idx].x;
+    let mut best_y = gs.players[defender_idx].y;
+    for &(x, y, s) in &candidates {
+        let c = cost(x, y, s);
+        if c < best_cost {
+            best_cost = c;
+            best_x = x;
+            best_y = y;
+        }
+    }
+    if search.refine && !candidates.is_empty() {
+        (best_x, best_y) = refine_position(
+            gs,
+            defender_idx,
+            &region,
+            grid_size,
+            (best_x, best_y),
+            |gs| {
+                let d = &gs.players[defender_idx];
+                combined_heat_map_sum(gs, grid_size, params, cache).map(|s| cost(d.x, d.y, s))
+            },
+        );
+    }
+
+    gs.players[defender_idx].x = best_x;
+    gs.players[defender_idx].y = best_y;
+    Some((best_x, best_y))
+}
+
+/// Evaluate every cell centre inside the `search` region around the offender
+/// with the given label as a position for the matching defender.
+///
+/// Returns the defender's index, the region and `(x, y, sum)` for each
+/// candidate that has a combined heat-map sum; the defender is left where it
+/// started.
+fn score_defender_candidates(
+    gs: &mut GameState,
+    grid_size: f64,
+    defender_label: &str,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    search: &DefenderSearch,
+) -> Option<(usize, SearchRegion, Vec<Candidate>)> {
     let (offender_x, offender_y) = {
         let o = gs.players.iter().find(|p| {
             !p.is_defender && !p.has_disc && p.label.as_deref() == Some(defender_label)
@@ -46,44 +213,263 @@ pub fn position_defender_optimal(
     })?;
 
     let field = gs.field.clone();
-    let r2 = DEFENDER_SEARCH_RADIUS_YARDS * DEFENDER_SEARCH_RADIUS_YARDS;
+    let (start_x, start_y) = (gs.players[defender_idx].x, gs.players[defender_idx].y);
+    let region = SearchRegion::new(gs, (offender_x, offender_y), (start_x, start_y), search);
 
     let num_cells_x = (field.total_length / grid_size).ceil() as usize;
     let num_cells_y = (field.field_width / grid_size).ceil() as usize;
 
-    let mut best_sum = f64::INFINITY;
-    let mut best_x = gs.players[defender_idx].x;
-    let mut best_y = gs.players[defender_idx].y;
-
+    let mut cells = Vec::new();
     for xi in 0..num_cells_x {
         for yi in 0..num_cells_y {
             let cx = xi as f64 * grid_size + grid_size / 2.0;
             let cy = yi as f64 * grid_size + grid_size / 2.0;
-            let dx = cx - offender_x;
-            let dy = cy - offender_y;
-            if dx * dx + dy * dy > r2 {
-                continue;
+            if region.contains(cx, cy) {
+                cells.push((cx, cy));
             }
+        }
+    }
+    // A short reach may hold no cell centre at all; the nearest reachable
+    // point always counts.
+    if region.reach.is_some() {
+        cells.push(region.project(start_x, start_y));
+    }
 
-            let clamped_x = cx.clamp(0.0, field.total_length);
-            let clamped_y = cy.clamp(0.0, field.field_width);
+    let mut candidates = Vec::new();
+    for (cx, cy) in cells {
+        let clamped_x = cx.clamp(0.0, field.total_length);
+        let clamped_y = cy.clamp(0.0, field.field_width);
 
-            gs.players[defender_idx].x = clamped_x;
-            gs.players[defender_idx].y = clamped_y;
+        gs.players[defender_idx].x = clamped_x;
+        gs.players[defender_idx].y = clamped_y;
 
-            if let Some(s) = combined_heat_map_sum(gs, grid_size) {
-                if s < best_sum {
-                    best_sum = s;
-                    best_x = clamped_x;
-                    best_y = clamped_y;
-                }
+        if let Some(s) = combined_heat_map_sum(gs, grid_size, params, cache) {
+            candidates.push((clamped_x, clamped_y, s));
+        }
+    }
+
+    gs.players[defender_idx].x = start_x;
+    gs.players[defender_idx].y = start_y;
+    Some((defender_idx, region, candidates))
+}
+
+/// Check a request's search settings.
+pub fn validate_search(search: &DefenderSearch) -> Result<(), String> {
+    if let Some(r) = search.radius_yards {
+        if !r.is_finite() || r <= 0.0 || r > DEFENDER_SEARCH_MAX_RADIUS_YARDS {
+            return Err(format!(
+                "search.radiusYards must be in (0, {DEFENDER_SEARCH_MAX_RADIUS_YARDS}], got {r}"
+            ));
+        }
+    }
+    if let Some(t) = search.reaction_seconds {
+        if !t.is_finite() || t <= 0.0 || t > DEFENDER_MAX_REACTION_SECONDS {
+            return Err(format!(
+                "search.reactionSeconds must be in (0, {DEFENDER_MAX_REACTION_SECONDS}], got {t}"
+            ));
+        }
+    }
+    for (name, value) in [
+        ("maxSpeedYps", search.max_speed_yps),
+        ("accelerationYps2", search.acceleration_yps2),
+    ] {
+        if let Some(v) = value {
+            if !v.is_finite() || v <= 0.0 {
+                return Err(format!("search.{name} must be positive, got {v}"));
             }
         }
     }
+    Ok(())
+}
 
-    gs.players[defender_idx].x = best_x;
-    gs.players[defender_idx].y = best_y;
-    Some((best_x, best_y))
+/// Check a request's hybrid blend weight.
+pub fn validate_blend_weight(weight: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&weight) {
+        return Err(format!("blendWeight must be in [0, 1], got {weight}"));
+    }
+    Ok(())
+}
+
+/// Distance (yards) a player starting from rest covers in `seconds`,
+/// accelerating at `acceleration` up to `max_speed`.
+pub fn reach_distance(seconds: f64, max_speed: f64, acceleration: f64) -> f64 {
+    let to_top_speed = max_speed / acceleration;
+    if seconds <= to_top_speed {
+        0.5 * acceleration * seconds * seconds
+    } else {
+        max_speed * seconds - max_speed * max_speed / (2.0 * acceleration)
+    }
+}
+
+/// Disc of `radius` around the offender, cut to the half-plane
+/// `(p − centre) · normal ≥ 0` for the half-disc shapes, and intersected with
+/// the disc the defender can reach when the search has a time budget.
+struct SearchRegion {
+    centre: (f64, f64),
+    radius: f64,
+    normal: Option<(f64, f64)>,
+    /// `(centre, radius)` of the reachable disc.
+    reach: Option<((f64, f64), f64)>,
+}
+
+impl SearchRegion {
+    fn new(
+        gs: &GameState,
+        offender: (f64, f64),
+        defender: (f64, f64),
+        search: &DefenderSearch,
+    ) -> Self {
+        let (dx, dy) = (gs.disc.x - offender.0, gs.disc.y - offender.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        // An offender standing on the disc has no under / deep side.
+        let toward_disc = (length > 0.0).then(|| (dx / length, dy / length));
+        let normal = match search.shape {
+            SearchShape::Circle => None,
+            SearchShape::GoalSide => Some((-1.0, 0.0)),
+            SearchShape::Under => toward_disc,
+            SearchShape::Deep => toward_disc.map(|(x, y)| (-x, -y)),
+        };
+        let mut region = Self {
+            centre: offender,
+            radius: search.radius_yards.unwrap_or(DEFENDER_SEARCH_RADIUS_YARDS),
+            normal,
+            reach: None,
+        };
+        let Some(seconds) = search.reaction_seconds else {
+            return region;
+        };
+
+        let reach = reach_distance(
+            seconds,
+            search.max_speed_yps.unwrap_or(DEFENDER_MAX_SPEED_YPS),
+            search
+                .acceleration_yps2
+                .unwrap_or(DEFENDER_ACCELERATION_YPS2),
+        );
+        let nearest = region.project_shape(defender.0, defender.1);
+        let (dx, dy) = (nearest.0 - defender.0, nearest.1 - defender.1);
+        let gap = (dx * dx + dy * dy).sqrt();
+        if gap <= reach {
+            region.reach = Some((defender, reach));
+            return region;
+        }
+        // Out of range: the only choice is to run straight at the region.
+        let run = (defender.0 + dx * reach / gap, defender.1 + dy * reach / gap);
+        Self {
+            centre: run,
+            radius: 0.0,
+            normal: None,
+            reach: Some((run, 0.0)),
+        }
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        let (dx, dy) = (x - self.centre.0, y - self.centre.1);
+        dx * dx + dy * dy <= self.radius * self.radius
+            && self.normal.is_none_or(|(nx, ny)| dx * nx + dy * ny >= 0.0)
+            && self.reach.is_none_or(|(c, r)| within(c, r, x, y))
+    }
+
+    /// A point of the region near `(x, y)`: the nearest one without a reach
+    /// limit, otherwise found by alternating projections onto the shape and
+    /// the reach disc (both convex, and known to overlap).
+    fn project(&self, x: f64, y: f64) -> (f64, f64) {
+        let Some((c, r)) = self.reach else {
+            return self.project_shape(x, y);
+        };
+        let mut p = (x, y);
+        for _ in 0..DEFENDER_REACH_PROJECTION_ITERATIONS {
+            p = self.project_shape(p.0, p.1);
+            if within(c, r, p.0, p.1) {
+                break;
+            }
+            p = project_disc(c, r, p.0, p.1);
+        }
+        p
+    }
+
+    /// Nearest point of the shape (ignoring the reach limit) to `(x, y)`.
+    /// The half-plane's edge runs through the centre, so clipping to it and
+    /// then pulling in radially is exact.
+    fn project_shape(&self, x: f64, y: f64) -> (f64, f64) {
+        let (mut dx, mut dy) = (x - self.centre.0, y - self.centre.1);
+        if let Some((nx, ny)) = self.normal {
+            let along = dx * nx + dy * ny;
+            if along < 0.0 {
+                dx -= along * nx;
+                dy -= along * ny;
+            }
+        }
+        project_disc(
+            self.centre,
+            self.radius,
+            self.centre.0 + dx,
+            self.centre.1 + dy,
+        )
+    }
+}
+
+/// Whether `(x, y)` lies in the disc of `radius` around `centre`, allowing
+/// for rounding in the projections.
+fn within(centre: (f64, f64), radius: f64, x: f64, y: f64) -> bool {
+    const SLACK: f64 = 1e-9;
+    (x - centre.0).hypot(y - centre.1) <= radius + SLACK
+}
+
+/// Nearest point of the disc of `radius` around `centre` to `(x, y)`.
+fn project_disc(centre: (f64, f64), radius: f64, x: f64, y: f64) -> (f64, f64) {
+    let (dx, dy) = (x - centre.0, y - centre.1);
+    let length = dx.hypot(dy);
+    if length <= radius {
+        return (x, y);
+    }
+    (
+        centre.0 + dx * radius / length,
+        centre.1 + dy * radius / length,
+    )
+}
+
+/// Nelder–Mead over the defender's position, starting from the best grid
+/// cell with half-cell steps.  `cost` is evaluated with the defender moved
+/// to the trial point; points outside the region (or field) are evaluated
+/// at their projection plus a penalty.  The defender is left where it
+/// started.
+fn refine_position<F>(
+    gs: &mut GameState,
+    defender_idx: usize,
+    region: &SearchRegion,
+    grid_size: f64,
+    start: (f64, f64),
+    mut cost: F,
+) -> (f64, f64)
+where
+    F: FnMut(&GameState) -> Option<f64>,
+{
+    let (total_length, field_width) = (gs.field.total_length, gs.field.field_width);
+    let place = |x: f64, y: f64| {
+        let (px, py) = region.project(x, y);
+        (px.clamp(0.0, total_length), py.clamp(0.0, field_width))
+    };
+    let original = (gs.players[defender_idx].x, gs.players[defender_idx].y);
+
+    let step = grid_size / 2.0;
+    let minimum = nelder_mead(
+        |v| {
+            let (px, py) = place(v[0], v[1]);
+            gs.players[defender_idx].x = px;
+            gs.players[defender_idx].y = py;
+            let stray = ((v[0] - px).powi(2) + (v[1] - py).powi(2)).sqrt();
+            cost(gs).unwrap_or(f64::INFINITY) + DEFENDER_REFINE_PENALTY_PER_YARD * stray
+        },
+        &[start.0, start.1],
+        &[step, step],
+        DEFENDER_REFINE_MAX_ITERATIONS,
+        DEFENDER_REFINE_TOLERANCE,
+    );
+
+    gs.players[defender_idx].x = original.0;
+    gs.players[defender_idx].y = original.1;
+    place(minimum.x[0], minimum.x[1])
 }
 
 /// Move the offender with the given label to a cell sampled from the combined
@@ -96,6 +482,28 @@ pub fn position_offender_optimal(
     gs: &mut GameState,
     grid_size: f64,
     offender_label: &str,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+) -> Option<(f64, f64)> {
+    position_offender_optimal_with_rng(
+        gs,
+        grid_size,
+        offender_label,
+        params,
+        cache,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// `position_offender_optimal` drawing from `rng`, so seeded callers (the
+/// simulator) are reproducible.
+pub fn position_offender_optimal_with_rng<R: Rng>(
+    gs: &mut GameState,
+    grid_size: f64,
+    offender_label: &str,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    rng: &mut R,
 ) -> Option<(f64, f64)> {
     let offender_idx = gs.players.iter().position(|p| {
         !p.is_defender && !p.has_disc && p.label.as_deref() == Some(offender_label)
@@ -108,11 +516,24 @@ pub fn position_offender_optimal(
     let num_cells_x = (field.total_length / grid_size).ceil() as usize;
     let num_cells_y = (field.field_width / grid_size).ceil() as usize;
 
-    let catch = get_catch_layer(num_cells_x, num_cells_y, grid_size, disc, &field);
-    let diff = get_difficulty_layer(num_cells_x, num_cells_y, grid_size, disc);
-    let (mark, _, _) =
-        get_marking_difficulty_layer(num_cells_x, num_cells_y, grid_size, players, disc)?;
-    let cov = get_coverage_layer(num_cells_x, num_cells_y, grid_size, players, disc);
+    let catch = cache.catch_layer(num_cells_x, num_cells_y, grid_size, gs, params);
+    let diff = cache.difficulty_layer(num_cells_x, num_cells_y, grid_size, gs, params);
+    let (mark, _, _) = cache.marking_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        players,
+        disc,
+        &params.mark,
+    )?;
+    let cov = get_coverage_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        players,
+        disc,
+        &params.coverage,
+    );
 
     // Build weighted candidates
     let mut squares: Vec<(f64, f64, f64)> = Vec::with_capacity(num_cells_x * num_cells_y);
@@ -132,7 +553,7 @@ pub fn position_offender_optimal(
     }
 
     // Weighted-random pick
-    let threshold = rand::thread_rng().gen::<f64>() * total;
+    let threshold = rng.gen::<f64>() * total;
     let mut cumul = 0.0_f64;
     let mut best_x = gs.players[offender_idx].x;
     let mut best_y = gs.players[offender_idx].y;
@@ -153,8 +574,8 @@ pub fn position_offender_optimal(
     Some((best_x, best_y))
 }
 
-/// Move the offender to the "stack" position: centre-width, 20 yards
-/// downfield (lower x) from the current disc position.
+/// Move the offender to the "stack" position: centre-width,
+/// `STACK_OFFSET_YARDS` (scaled to the field) downfield from the disc.
 pub fn position_offender_stack(gs: &mut GameState) -> Option<(f64, f64)> {
     let offender_idx = gs
         .players
@@ -162,10 +583,207 @@ pub fn position_offender_stack(gs: &mut GameState) -> Option<(f64, f64)> {
         .position(|p| !p.is_defender && !p.has_disc)?;
     let field = gs.field.clone();
 
-    let stack_x = (gs.disc.x - 20.0).clamp(0.0, field.total_length);
+    let stack_x =
+        (gs.disc.x - STACK_OFFSET_YARDS * field.length_scale()).clamp(0.0, field.total_length);
     let stack_y = field.field_width / 2.0;
 
     gs.players[offender_idx].x = stack_x;
     gs.players[offender_idx].y = stack_y;
     Some((stack_x, stack_y))
 }
+
+/// Move the defender with the given label to the position predicted by the
+/// k-NN model: the offender's position plus the weighted mean offset of the
+/// `k` most similar recorded samples.
+///
+/// Returns the new `(x, y)` position, or `None` when there is no defender or
+/// offender with that label or too few training samples.
+pub fn position_defender_learned(
+    gs: &mut GameState,
+    defender_label: &str,
+    samples: &[DefenderSample],
+    k: usize,
+) -> Option<(f64, f64)> {
+    let defender_idx = gs.players.iter().position(|p| {
+        p.is_defender && !p.is_mark && p.label.as_deref() == Some(defender_label)
+    })?;
+    let (x, y) = learned_defender_target(gs, defender_label, samples, k)?;
+    gs.players[defender_idx].x = x;
+    gs.players[defender_idx].y = y;
+    Some((x, y))
+}
+
+/// k-NN predicted position for the defender with the given label, clamped to
+/// the field, without moving anyone.
+fn learned_defender_target(
+    gs: &GameState,
+    defender_label: &str,
+    samples: &[DefenderSample],
+    k: usize,
+) -> Option<(f64, f64)> {
+    let offender = gs.players.iter().find(|p| {
+        !p.is_defender && !p.has_disc && p.label.as_deref() == Some(defender_label)
+    })?;
+    let features = features_for(gs, defender_label)?;
+    let (dx, dy) = predict_offset(samples, &features, k)?;
+    Some((
+        (offender.x + dx).clamp(0.0, gs.field.total_length),
+        (offender.y + dy).clamp(0.0, gs.field.field_width),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FieldPreset;
+    use crate::models::{Disc, Player, Wind};
+
+    const GRID: f64 = 2.0;
+    const SLACK: f64 = 1e-6;
+
+    fn player(id: &str, x: f64, y: f64, defender: bool, label: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: id == "m",
+            label: Some(label.to_string()),
+        }
+    }
+
+    fn state() -> GameState {
+        GameState {
+            players: vec![
+                player("t", 80.0, 20.0, false, "0"),
+                player("m", 80.0, 21.0, true, "0"),
+                player("o1", 60.0, 10.0, false, "1"),
+                player("d1", 59.0, 11.0, true, "1"),
+                player("o2", 50.0, 30.0, false, "2"),
+                player("d2", 49.0, 29.0, true, "2"),
+            ],
+            disc: Disc {
+                x: 80.0,
+                y: 20.0,
+                holder_id: Some("t".to_string()),
+            },
+            field: FieldPreset::Usa.dimensions(),
+            stall: 0,
+            wind: Wind::default(),
+        }
+    }
+
+    fn place(search: &DefenderSearch) -> (f64, f64) {
+        let mut gs = state();
+        let params = HeatMapParams::default();
+        let cache = LayerCache::disabled();
+        position_defender_optimal(&mut gs, GRID, "1", &params, &cache, search)
+            .expect("defender 1 is placed")
+    }
+
+    #[test]
+    fn half_disc_results_stay_in_their_half() {
+        let (ox, oy) = (60.0, 10.0);
+        let length = (80.0_f64 - ox).hypot(20.0 - oy);
+        let toward_disc = ((80.0 - ox) / length, (20.0 - oy) / length);
+        let cases = [
+            (SearchShape::GoalSide, (-1.0, 0.0)),
+            (SearchShape::Under, toward_disc),
+            (SearchShape::Deep, (-toward_disc.0, -toward_disc.1)),
+        ];
+        for refine in [false, true] {
+            for (shape, (nx, ny)) in cases {
+                let search = DefenderSearch {
+                    shape,
+                    refine,
+                    ..DefenderSearch::default()
+                };
+                let (x, y) = place(&search);
+                let (dx, dy) = (x - ox, y - oy);
+                assert!(
+                    dx.hypot(dy) <= DEFENDER_SEARCH_RADIUS_YARDS + SLACK,
+                    "{shape:?} (refine {refine}) left the disc: ({x}, {y})"
+                );
+                assert!(
+                    dx * nx + dy * ny >= -SLACK,
+                    "{shape:?} (refine {refine}) left its half: ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn refinement_is_no_worse_than_the_best_cell() {
+        let params = HeatMapParams::default();
+        let cache = LayerCache::disabled();
+        let sum_at = |search: &DefenderSearch| {
+            let mut gs = state();
+            position_defender_optimal(&mut gs, GRID, "1", &params, &cache, search).unwrap();
+            combined_heat_map_sum(&gs, GRID, &params, &cache).unwrap()
+        };
+        let grid = sum_at(&DefenderSearch::default());
+        let refined = sum_at(&DefenderSearch {
+            refine: true,
+            ..DefenderSearch::default()
+        });
+        assert!(refined <= grid + SLACK, "refined {refined} > grid {grid}");
+    }
+
+    #[test]
+    fn reach_distance_accelerates_then_holds_top_speed() {
+        // 7 yd/s at 5 yd/s² is reached after 1.4 s and 4.9 yards.
+        assert!((reach_distance(1.0, 7.0, 5.0) - 2.5).abs() < 1e-12);
+        assert!((reach_distance(1.4, 7.0, 5.0) - 4.9).abs() < 1e-12);
+        assert!((reach_distance(3.0, 7.0, 5.0) - 16.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reaction_results_stay_within_reach_of_the_start() {
+        let start = (59.0, 11.0);
+        for seconds in [0.2, 0.5, 1.0] {
+            let reach = reach_distance(seconds, DEFENDER_MAX_SPEED_YPS, DEFENDER_ACCELERATION_YPS2);
+            for shape in [SearchShape::Circle, SearchShape::Under] {
+                for refine in [false, true] {
+                    let search = DefenderSearch {
+                        shape,
+                        refine,
+                        reaction_seconds: Some(seconds),
+                        ..DefenderSearch::default()
+                    };
+                    let (x, y) = place(&search);
+                    let moved = (x - start.0).hypot(y - start.1);
+                    assert!(
+                        moved <= reach + SLACK,
+                        "{shape:?} (refine {refine}) moved {moved} > {reach} in {seconds} s"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_rejects_non_positive_speed_and_acceleration() {
+        for bad in [0.0, -1.0, f64::NAN] {
+            let acceleration = DefenderSearch {
+                acceleration_yps2: Some(bad),
+                ..DefenderSearch::default()
+            };
+            assert!(validate_search(&acceleration).is_err(), "acceleration {bad}");
+            let speed = DefenderSearch {
+                max_speed_yps: Some(bad),
+                ..DefenderSearch::default()
+            };
+            assert!(validate_search(&speed).is_err(), "speed {bad}");
+        }
+        let ok = DefenderSearch {
+            reaction_seconds: Some(1.0),
+            max_speed_yps: Some(8.0),
+            acceleration_yps2: Some(4.0),
+            ..DefenderSearch::default()
+        };
+        assert!(validate_search(&ok).is_ok());
+    }
+}
@@ -4,7 +4,20 @@
 //! of constants.  Tweak the values in those blocks to reshape a layer without
 //! digging into the formula code.
 
+// Grids are indexed by cell coordinates on purpose: the same `(x, y)` is used
+// to derive the cell centre and to index several parallel layers.
+#![allow(clippy::needless_range_loop)]
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::LayerCache;
+use crate::completion::{get_completion_layer, CompletionParams};
+use crate::epv::{get_epv_layer, get_expected_gain_layer, EpvParams};
 use crate::models::{Disc, FieldDimensions, GameState, HeatMapData, HeatMapModes, Player};
+use crate::wind::{deep_value_factor, difficulty_factor, WindParams};
 
 // ============================================================================
 // CATCH-VALUE LAYER CONSTANTS
@@ -98,7 +111,17 @@ const MARK_DISTANCE_STRENGTH: f64 = 3.0;
 // heat-map layers (coverage, marking thrower, etc.).
 // ============================================================================
 
-const SIDELINE_X_MIN: f64 = 0.0; // players with x < 0 are in the sideline area
+pub(crate) const SIDELINE_X_MIN: f64 = 0.0; // players with x < 0 are in the sideline area
+
+// ============================================================================
+// GRID LIMITS
+// Cell sizes (yards) accepted wherever a request or flag sets `gridSize`.
+// Below the minimum a full-field map runs to hundreds of thousands of cells
+// per layer; above the maximum it is too coarse to mean anything.
+// ============================================================================
+
+pub const MIN_GRID_SIZE: f64 = 0.25;
+pub const MAX_GRID_SIZE: f64 = 10.0;
 
 // ============================================================================
 // COVERAGE LAYER CONSTANTS
@@ -120,6 +143,220 @@ const COVERAGE_SEMI_COVERED_VALUE: f64 = 0.5;
 /// Layer value when the area is open.
 const COVERAGE_OPEN_VALUE: f64 = 1.0;
 
+// ============================================================================
+// RESET-SPACE LAYER CONSTANTS
+// Where can a handler get the disc back (dump / swing) behind the thrower?
+// Only cells at most `CATCH_MAX_THROWBACK_YARDS` behind the disc qualify.
+// ============================================================================
+
+/// Ideal dump depth behind the disc (yards).
+const RESET_IDEAL_BACK_YARDS: f64 = 4.0;
+
+/// Ideal lateral distance of the dump from the disc (yards).
+const RESET_IDEAL_LATERAL_YARDS: f64 = 5.0;
+
+/// Lateral distance (yards) from the ideal at which the value reaches 0.
+const RESET_LATERAL_RANGE_YARDS: f64 = 8.0;
+
+/// Cells up to this far *ahead* of the disc still count (flat swings).
+const RESET_FORWARD_YARDS: f64 = 2.0;
+
+/// Resets shorter than this ramp down to 0 (a handler on top of the thrower
+/// is no outlet).
+const RESET_MIN_DISTANCE_YARDS: f64 = 3.0;
+
+// ============================================================================
+// STALL CONSTANTS
+// How the layers shift as the stall count rises.  At stall 0 every layer is
+// exactly as without a count.
+// ============================================================================
+
+/// Stall count at which the thrower is stalled out.
+const STALL_MAX_COUNT: f64 = 10.0;
+
+/// Share of the catch layer replaced by reset space at the final count.
+const STALL_RESET_WEIGHT: f64 = 0.6;
+
+/// Shape of the reset shift: 2 keeps early counts almost unchanged and
+/// ramps up late.
+const STALL_RESET_EXPONENT: f64 = 2.0;
+
+/// Share of the throw difficulty ignored at the final count (a thrower near
+/// stall accepts harder throws).
+const STALL_DIFFICULTY_RELIEF: f64 = 0.5;
+
+// ============================================================================
+// Runtime parameters
+// The constants above are the defaults; a config file may override any of
+// them per server without recompiling.
+// ============================================================================
+
+/// Tunable heat-map parameters, grouped by layer.  Missing fields fall back to
+/// the constants above.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct HeatMapParams {
+    pub catch: CatchParams,
+    pub difficulty: DifficultyParams,
+    pub mark: MarkParams,
+    pub coverage: CoverageParams,
+    pub completion: CompletionParams,
+    pub epv: EpvParams,
+    pub reset: ResetParams,
+    pub stall: StallParams,
+    pub wind: WindParams,
+}
+
+impl HeatMapParams {
+    /// The parameters are stated for the reference (USA Ultimate) field.
+    /// Stretch the ones tied to the field's geometry — sideline band, mark
+    /// force point, throwback and reset-space extents — to `field`.  Those
+    /// describing players and the disc (speeds, range, pass and huck
+    /// lengths) are left alone.  Borrowed unchanged on the reference field.
+    pub fn for_field(&self, field: &FieldDimensions) -> Cow<'_, HeatMapParams> {
+        let (length, width) = (field.length_scale(), field.width_scale());
+        if length == 1.0 && width == 1.0 {
+            return Cow::Borrowed(self);
+        }
+        let mut p = self.clone();
+        p.catch.side_boundary_yards *= width;
+        p.catch.max_throwback_yards *= length;
+        p.mark.force_x *= length;
+        p.mark.force_y *= width;
+        p.mark.distance_scale *= (length * width).sqrt();
+        p.reset.ideal_back_yards *= length;
+        p.reset.ideal_lateral_yards *= width;
+        p.reset.lateral_range_yards *= width;
+        Cow::Owned(p)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct CatchParams {
+    pub position_scale: f64,
+    pub side_boundary_yards: f64,
+    pub sideline_linear_penalty: f64,
+    pub sideline_steep_coeff: f64,
+    pub sideline_exponent: f64,
+    pub min_pass_distance_yards: f64,
+    pub short_pass_exponent: f64,
+    pub max_throwback_yards: f64,
+}
+
+impl Default for CatchParams {
+    fn default() -> Self {
+        Self {
+            position_scale: CATCH_POSITION_SCALE,
+            side_boundary_yards: CATCH_SIDE_BOUNDARY_YARDS,
+            sideline_linear_penalty: CATCH_SIDELINE_LINEAR_PENALTY,
+            sideline_steep_coeff: CATCH_SIDELINE_STEEP_COEFF,
+            sideline_exponent: CATCH_SIDELINE_EXPONENT,
+            min_pass_distance_yards: CATCH_MIN_PASS_DISTANCE_YARDS,
+            short_pass_exponent: CATCH_SHORT_PASS_EXPONENT,
+            max_throwback_yards: CATCH_MAX_THROWBACK_YARDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct DifficultyParams {
+    pub distance_scale: f64,
+    pub post_norm_min: f64,
+    pub post_norm_divisor: f64,
+}
+
+impl Default for DifficultyParams {
+    fn default() -> Self {
+        Self {
+            distance_scale: DIFFICULTY_DISTANCE_SCALE,
+            post_norm_min: DIFFICULTY_POST_NORM_MIN,
+            post_norm_divisor: DIFFICULTY_POST_NORM_DIVISOR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct MarkParams {
+    pub easy_angle_radians: f64,
+    pub force_x: f64,
+    pub force_y: f64,
+    pub distance_scale: f64,
+    pub distance_strength: f64,
+}
+
+impl Default for MarkParams {
+    fn default() -> Self {
+        Self {
+            easy_angle_radians: MARK_EASY_ANGLE_RADIANS,
+            force_x: MARK_FORCE_X,
+            force_y: MARK_FORCE_Y,
+            distance_scale: MARK_DISTANCE_SCALE,
+            distance_strength: MARK_DISTANCE_STRENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct CoverageParams {
+    pub defender_handicap_yards: f64,
+    pub semi_covered_value: f64,
+}
+
+impl Default for CoverageParams {
+    fn default() -> Self {
+        Self {
+            defender_handicap_yards: COVERAGE_DEFENDER_HANDICAP_YARDS,
+            semi_covered_value: COVERAGE_SEMI_COVERED_VALUE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ResetParams {
+    pub ideal_back_yards: f64,
+    pub ideal_lateral_yards: f64,
+    pub lateral_range_yards: f64,
+    pub forward_yards: f64,
+    pub min_distance_yards: f64,
+}
+
+impl Default for ResetParams {
+    fn default() -> Self {
+        Self {
+            ideal_back_yards: RESET_IDEAL_BACK_YARDS,
+            ideal_lateral_yards: RESET_IDEAL_LATERAL_YARDS,
+            lateral_range_yards: RESET_LATERAL_RANGE_YARDS,
+            forward_yards: RESET_FORWARD_YARDS,
+            min_distance_yards: RESET_MIN_DISTANCE_YARDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct StallParams {
+    pub max_count: f64,
+    pub reset_weight: f64,
+    pub reset_exponent: f64,
+    pub difficulty_relief: f64,
+}
+
+impl Default for StallParams {
+    fn default() -> Self {
+        Self {
+            max_count: STALL_MAX_COUNT,
+            reset_weight: STALL_RESET_WEIGHT,
+            reset_exponent: STALL_RESET_EXPONENT,
+            difficulty_relief: STALL_DIFFICULTY_RELIEF,
+        }
+    }
+}
+
 // ============================================================================
 // Per-cell helper functions
 // ============================================================================
@@ -130,12 +367,18 @@ const COVERAGE_OPEN_VALUE: f64 = 1.0;
 ///   1. **Position value** — how far the catch advances the disc toward the
 ///      scoring end zone (capped to [SCALE, 1.0]).
 ///   2. **Width (sideline) penalty** — cells near a sideline are worth less.
-///   3. **Short-pass penalty** — passes shorter than CATCH_MIN_PASS_DISTANCE_YARDS
+///   3. **Short-pass penalty** — passes shorter than `min_pass_distance_yards`
 ///      ramp from ≈0 up to 1.0 using a polynomial curve.
 ///   4. **Backward-pass penalty** — the *same* polynomial curve used for the
 ///      sideline penalty is applied along the throwback axis.  Catches more
-///      than CATCH_MAX_THROWBACK_YARDS behind the disc return 0.
-pub fn calculate_catch_value(x: f64, y: f64, disc: &Disc, field: &FieldDimensions) -> f64 {
+///      than `max_throwback_yards` behind the disc return 0.
+pub fn calculate_catch_value(
+    x: f64,
+    y: f64,
+    disc: &Disc,
+    field: &FieldDimensions,
+    params: &CatchParams,
+) -> f64 {
     let scoring_end = field.end_zone_depth; // x ≤ this is inside the scoring end zone
 
     if x <= scoring_end {
@@ -145,14 +388,14 @@ pub fn calculate_catch_value(x: f64, y: f64, disc: &Disc, field: &FieldDimension
     // ── 1. Backward-pass penalty ────────────────────────────────────────────
     // throwback: how many yards behind the disc the cell lies (0 when forward)
     let throwback = (x - disc.x).max(0.0);
-    if throwback >= CATCH_MAX_THROWBACK_YARDS {
+    if throwback >= params.max_throwback_yards {
         return 0.0; // too far behind — no value
     }
     // Same curve shape as the sideline penalty; t = 0 at disc, 1 at max throwback
     let backward_factor = {
-        let t = throwback / CATCH_MAX_THROWBACK_YARDS;
-        1.0 - t * CATCH_SIDELINE_LINEAR_PENALTY
-            - CATCH_SIDELINE_STEEP_COEFF * t.powf(CATCH_SIDELINE_EXPONENT)
+        let t = throwback / params.max_throwback_yards;
+        1.0 - t * params.sideline_linear_penalty
+            - params.sideline_steep_coeff * t.powf(params.sideline_exponent)
     };
 
     // ── 2. Short-pass penalty ────────────────────────────────────────────────
@@ -160,26 +403,26 @@ pub fn calculate_catch_value(x: f64, y: f64, disc: &Disc, field: &FieldDimension
     let dx = x - disc.x;
     let dy = y - disc.y;
     let pass_dist = (dx * dx + dy * dy).sqrt();
-    let short_pass_factor = (pass_dist / CATCH_MIN_PASS_DISTANCE_YARDS)
+    let short_pass_factor = (pass_dist / params.min_pass_distance_yards)
         .min(1.0)
-        .powf(CATCH_SHORT_PASS_EXPONENT);
+        .powf(params.short_pass_exponent);
 
     // ── 3. Position value ───────────────────────────────────────────────────
     // Forward progress toward the end zone, shifted into [SCALE, 1.0].
     // Cells behind the disc (throwback > 0) clamp to 0 progress → minimum 0.5.
     let raw_progress = ((disc.x - x) / field.field_length).clamp(0.0, 1.0);
-    let position_value = raw_progress * CATCH_POSITION_SCALE + CATCH_POSITION_SCALE;
+    let position_value = raw_progress * params.position_scale + params.position_scale;
 
     // ── 4. Width (sideline) penalty ─────────────────────────────────────────
     let field_center_y = field.field_width / 2.0;
     let dist_from_center = (y - field_center_y).abs();
-    let outer_band_start = field_center_y - CATCH_SIDE_BOUNDARY_YARDS;
+    let outer_band_start = field_center_y - params.side_boundary_yards;
 
     let center_bonus = if dist_from_center > outer_band_start {
         let dist_from_sideline = field_center_y - dist_from_center;
-        let t = 1.0 - (dist_from_sideline / CATCH_SIDE_BOUNDARY_YARDS); // 0 at boundary, 1 at sideline
-        1.0 - t * CATCH_SIDELINE_LINEAR_PENALTY
-            - CATCH_SIDELINE_STEEP_COEFF * t.powf(CATCH_SIDELINE_EXPONENT)
+        let t = 1.0 - (dist_from_sideline / params.side_boundary_yards); // 0 at boundary, 1 at sideline
+        1.0 - t * params.sideline_linear_penalty
+            - params.sideline_steep_coeff * t.powf(params.sideline_exponent)
     } else {
         1.0
     };
@@ -189,25 +432,31 @@ pub fn calculate_catch_value(x: f64, y: f64, disc: &Disc, field: &FieldDimension
 
 /// Raw throw difficulty at `(x, y)` — purely a function of distance from the
 /// disc.  Normalised internally by `get_difficulty_layer`.
-pub fn calculate_difficulty_at(x: f64, y: f64, disc: &Disc) -> f64 {
+pub fn calculate_difficulty_at(x: f64, y: f64, disc: &Disc, params: &DifficultyParams) -> f64 {
     let dx = x - disc.x;
     let dy = y - disc.y;
     let dist = (dx * dx + dy * dy).sqrt();
     if dist <= 0.0 {
         return 0.0;
     }
-    dist / DIFFICULTY_DISTANCE_SCALE
+    dist / params.distance_scale
 }
 
 /// Ease of throwing to `(target_x, target_y)` from `(thrower_x, thrower_y)`
 /// given the mark's forcing direction.
 /// Returns 0 (hardest) when throwing directly into the mark, 1 (easiest)
-/// when the throw is ≥ MARK_EASY_ANGLE_RADIANS off the mark.
-pub fn calculate_ease_at(thrower_x: f64, thrower_y: f64, target_x: f64, target_y: f64) -> f64 {
+/// when the throw is ≥ `easy_angle_radians` off the mark.
+pub fn calculate_ease_at(
+    thrower_x: f64,
+    thrower_y: f64,
+    target_x: f64,
+    target_y: f64,
+    params: &MarkParams,
+) -> f64 {
     // Direction the mark is trying to force the throw
     let (mdx, mdy) = {
-        let dx = MARK_FORCE_X - thrower_x;
-        let dy = MARK_FORCE_Y - thrower_y;
+        let dx = params.force_x - thrower_x;
+        let dy = params.force_y - thrower_y;
         let len = (dx * dx + dy * dy).sqrt();
         if len < 0.001 {
             return 1.0; // degenerate mark position → unconstrained
@@ -230,10 +479,10 @@ pub fn calculate_ease_at(thrower_x: f64, thrower_y: f64, target_x: f64, target_y
     let cross = mdx * tdy - mdy * tdx;
     let abs_angle = cross.atan2(dot).abs();
 
-    if abs_angle >= MARK_EASY_ANGLE_RADIANS {
+    if abs_angle >= params.easy_angle_radians {
         1.0
     } else {
-        abs_angle / MARK_EASY_ANGLE_RADIANS
+        abs_angle / params.easy_angle_radians
     }
 }
 
@@ -246,15 +495,49 @@ pub fn calculate_marking_difficulty_at(
     target_x: f64,
     target_y: f64,
     disc: &Disc,
+    params: &MarkParams,
 ) -> f64 {
-    let ease = calculate_ease_at(thrower_x, thrower_y, target_x, target_y);
+    let ease = calculate_ease_at(thrower_x, thrower_y, target_x, target_y, params);
     let dx = target_x - disc.x;
     let dy = target_y - disc.y;
     let dist = (dx * dx + dy * dy).sqrt();
-    let distance_factor = (1.0 - dist / (MARK_DISTANCE_SCALE * MARK_DISTANCE_STRENGTH)).max(0.0);
+    let distance_factor =
+        (1.0 - dist / (params.distance_scale * params.distance_strength)).max(0.0);
     1.0 - (1.0 - ease) * distance_factor
 }
 
+/// Reset-space value of `(x, y)` in [0, 1]: 1 at the ideal dump spot
+/// (`ideal_back_yards` behind and `ideal_lateral_yards` to either side of
+/// the disc), falling to 0 beyond `max_throwback_yards` behind, more than
+/// `forward_yards` ahead, or too close to the thrower.
+pub fn calculate_reset_value(
+    x: f64,
+    y: f64,
+    disc: &Disc,
+    max_throwback_yards: f64,
+    params: &ResetParams,
+) -> f64 {
+    // Positive when the cell is behind the disc (x grows away from the
+    // scoring end zone).
+    let back = x - disc.x;
+    if back < -params.forward_yards || back > max_throwback_yards {
+        return 0.0;
+    }
+    let lateral = (y - disc.y).abs();
+    let dist = (back * back + lateral * lateral).sqrt();
+
+    let back_factor = (1.0 - (back - params.ideal_back_yards).abs() / max_throwback_yards).max(0.0);
+    let lateral_factor =
+        (1.0 - (lateral - params.ideal_lateral_yards).abs() / params.lateral_range_yards).max(0.0);
+    let distance_factor = (dist / params.min_distance_yards).min(1.0);
+    back_factor * lateral_factor * distance_factor
+}
+
+/// Stall pressure in [0, 1]: 0 at the start of the count, 1 at stall-out.
+pub fn stall_pressure(stall: u32, params: &StallParams) -> f64 {
+    (stall as f64 / params.max_count).clamp(0.0, 1.0)
+}
+
 // ============================================================================
 // Layer builders — fill a 2-D grid for the whole field
 // ============================================================================
@@ -266,13 +549,14 @@ pub fn get_catch_layer(
     grid_size: f64,
     disc: &Disc,
     field: &FieldDimensions,
+    params: &CatchParams,
 ) -> Vec<Vec<f64>> {
     let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
     for x in 0..num_cells_x {
         for y in 0..num_cells_y {
             let cx = x as f64 * grid_size + grid_size / 2.0;
             let cy = y as f64 * grid_size + grid_size / 2.0;
-            values[x][y] = calculate_catch_value(cx, cy, disc, field);
+            values[x][y] = calculate_catch_value(cx, cy, disc, field, params);
         }
     }
     values
@@ -286,6 +570,7 @@ pub fn get_difficulty_layer(
     num_cells_y: usize,
     grid_size: f64,
     disc: &Disc,
+    params: &DifficultyParams,
 ) -> Vec<Vec<f64>> {
     let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
     let mut max_difficulty = 0.0_f64;
@@ -294,7 +579,7 @@ pub fn get_difficulty_layer(
         for y in 0..num_cells_y {
             let cx = x as f64 * grid_size + grid_size / 2.0;
             let cy = y as f64 * grid_size + grid_size / 2.0;
-            let d = calculate_difficulty_at(cx, cy, disc);
+            let d = calculate_difficulty_at(cx, cy, disc, params);
             values[x][y] = d;
             if d > max_difficulty {
                 max_difficulty = d;
@@ -305,8 +590,8 @@ pub fn get_difficulty_layer(
     if max_difficulty > 0.0 {
         for x in 0..num_cells_x {
             for y in 0..num_cells_y {
-                values[x][y] = (values[x][y] / max_difficulty).max(DIFFICULTY_POST_NORM_MIN)
-                    / DIFFICULTY_POST_NORM_DIVISOR;
+                values[x][y] = (values[x][y] / max_difficulty).max(params.post_norm_min)
+                    / params.post_norm_divisor;
             }
         }
     }
@@ -322,6 +607,7 @@ pub fn get_marking_difficulty_layer(
     grid_size: f64,
     players: &[Player],
     disc: &Disc,
+    params: &MarkParams,
 ) -> Option<(Vec<Vec<f64>>, f64, f64)> {
     let thrower = players.iter().find(|p| p.has_disc)?;
     if thrower.x < SIDELINE_X_MIN {
@@ -334,12 +620,135 @@ pub fn get_marking_difficulty_layer(
         for y in 0..num_cells_y {
             let cx = x as f64 * grid_size + grid_size / 2.0;
             let cy = y as f64 * grid_size + grid_size / 2.0;
-            values[x][y] = calculate_marking_difficulty_at(tx, ty, cx, cy, disc);
+            values[x][y] = calculate_marking_difficulty_at(tx, ty, cx, cy, disc, params);
         }
     }
     Some((values, tx, ty))
 }
 
+/// Reset-space layer: `values[x][y]` in [0, 1] (see `calculate_reset_value`).
+pub fn get_reset_space_layer(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    disc: &Disc,
+    max_throwback_yards: f64,
+    params: &ResetParams,
+) -> Vec<Vec<f64>> {
+    let mut values = vec![vec![0.0_f64; num_cells_y]; num_cells_x];
+    for x in 0..num_cells_x {
+        for y in 0..num_cells_y {
+            let cx = x as f64 * grid_size + grid_size / 2.0;
+            let cy = y as f64 * grid_size + grid_size / 2.0;
+            values[x][y] = calculate_reset_value(cx, cy, disc, max_throwback_yards, params);
+        }
+    }
+    values
+}
+
+/// Catch layer adjusted for the rest of the game state:
+///   * **wind** — deep space is worth more downwind and less upwind (see
+///     `wind::deep_value_factor`);
+///   * **stall** — as the count rises, a growing share
+///     (`reset_weight · pressure^reset_exponent`) of each cell's value comes
+///     from reset space instead.
+///
+/// Identical to `get_catch_layer` in calm air at stall 0.
+pub fn get_catch_layer_for_state(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    game_state: &GameState,
+    params: &HeatMapParams,
+) -> Vec<Vec<f64>> {
+    let disc = &game_state.disc;
+    let mut values = get_catch_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        disc,
+        &game_state.field,
+        &params.catch,
+    );
+    if !game_state.wind.is_calm() {
+        for x in 0..num_cells_x {
+            for y in 0..num_cells_y {
+                let cx = x as f64 * grid_size + grid_size / 2.0;
+                let cy = y as f64 * grid_size + grid_size / 2.0;
+                let factor =
+                    deep_value_factor(&game_state.wind, cx - disc.x, cy - disc.y, &params.wind);
+                values[x][y] = (values[x][y] * factor).clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    let pressure = stall_pressure(game_state.stall, &params.stall);
+    if pressure <= 0.0 {
+        return values;
+    }
+
+    let w = params.stall.reset_weight * pressure.powf(params.stall.reset_exponent);
+    let reset = get_reset_space_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        disc,
+        params.catch.max_throwback_yards,
+        &params.reset,
+    );
+    for x in 0..num_cells_x {
+        for y in 0..num_cells_y {
+            values[x][y] = (1.0 - w) * values[x][y] + w * reset[x][y];
+        }
+    }
+    values
+}
+
+/// Difficulty layer adjusted for the rest of the game state:
+///   * **wind** — upwind and crosswind throws get harder (see
+///     `wind::difficulty_factor`), capped at 1;
+///   * **stall** — scaled down by up to `difficulty_relief` at stall-out.
+///
+/// Identical to `get_difficulty_layer` in calm air at stall 0.
+pub fn get_difficulty_layer_for_state(
+    num_cells_x: usize,
+    num_cells_y: usize,
+    grid_size: f64,
+    game_state: &GameState,
+    params: &HeatMapParams,
+) -> Vec<Vec<f64>> {
+    let disc = &game_state.disc;
+    let mut values = get_difficulty_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        disc,
+        &params.difficulty,
+    );
+    if !game_state.wind.is_calm() {
+        for x in 0..num_cells_x {
+            for y in 0..num_cells_y {
+                let cx = x as f64 * grid_size + grid_size / 2.0;
+                let cy = y as f64 * grid_size + grid_size / 2.0;
+                let factor =
+                    difficulty_factor(&game_state.wind, cx - disc.x, cy - disc.y, &params.wind);
+                values[x][y] = (values[x][y] * factor).min(1.0);
+            }
+        }
+    }
+
+    let pressure = stall_pressure(game_state.stall, &params.stall);
+    if pressure > 0.0 {
+        let relief = 1.0 - params.stall.difficulty_relief * pressure;
+        for column in &mut values {
+            for v in column {
+                *v *= relief;
+            }
+        }
+    }
+    values
+}
+
 /// Coverage layer: `values[x][y]` in {0.0, 0.5, 1.0}.
 /// Excludes the disc-holder (thrower) and the mark from both sides so the
 /// layer reflects downfield open/covered areas only.  Players in the
@@ -350,6 +759,7 @@ pub fn get_coverage_layer(
     grid_size: f64,
     players: &[Player],
     disc: &Disc,
+    params: &CoverageParams,
 ) -> Vec<Vec<f64>> {
     let offense: Vec<&Player> = players
         .iter()
@@ -379,7 +789,7 @@ pub fn get_coverage_layer(
                 .iter()
                 .map(|p| ((cx - p.x).powi(2) + (cy - p.y).powi(2)).sqrt())
                 .fold(f64::INFINITY, f64::min)
-                + COVERAGE_DEFENDER_HANDICAP_YARDS;
+                + params.defender_handicap_yards;
 
             let from_closer = if min_off >= min_def {
                 COVERAGE_FULLY_COVERED_VALUE
@@ -387,7 +797,7 @@ pub fn get_coverage_layer(
                 COVERAGE_OPEN_VALUE
             };
             let from_half = if min_def < disc_to_sq / 2.0 {
-                COVERAGE_SEMI_COVERED_VALUE
+                params.semi_covered_value
             } else {
                 COVERAGE_OPEN_VALUE
             };
@@ -402,9 +812,22 @@ pub fn get_coverage_layer(
 // Combined heat map
 // ============================================================================
 
+/// Check a requested cell size against the grid limits.  The message names
+/// no field, so callers can prefix `gridSize`, `--grid-size`, ...
+pub fn validate_grid_size(grid_size: f64) -> Result<(), String> {
+    if (MIN_GRID_SIZE..=MAX_GRID_SIZE).contains(&grid_size) {
+        Ok(())
+    } else {
+        Err(format!(
+            "must be between {MIN_GRID_SIZE} and {MAX_GRID_SIZE} yards, got {grid_size}"
+        ))
+    }
+}
+
 /// Compute the product-combined heat map from whichever layers are enabled.
 /// Difficulty is inverted (1 − v) before multiplying so green = good for
-/// the offence on all layers.
+/// the offence on all layers.  Expected gain is never combined: when it is
+/// enabled the other layers are ignored and it is returned on its own.
 /// Returns `None` when no layers are enabled or there is no disc holder for
 /// the marking layer.
 pub fn calculate_heat_map(
@@ -412,7 +835,24 @@ pub fn calculate_heat_map(
     modes: &HeatMapModes,
     normalize: bool,
     grid_size: f64,
+    params: &HeatMapParams,
+    cache: &LayerCache,
 ) -> Option<HeatMapData> {
+    let gain_only = HeatMapModes {
+        catch: false,
+        difficulty: false,
+        marking_difficulty: false,
+        coverage: false,
+        completion: false,
+        epv: false,
+        expected_gain: true,
+        reset_space: false,
+    };
+    let modes = if modes.expected_gain {
+        &gain_only
+    } else {
+        modes
+    };
     let field = &game_state.field;
     let disc = &game_state.disc;
     let players = &game_state.players;
@@ -422,7 +862,7 @@ pub fn calculate_heat_map(
 
     struct Layer {
         key: &'static str,
-        values: Vec<Vec<f64>>,
+        values: Arc<Vec<Vec<f64>>>,
     }
 
     let mut layers: Vec<Layer> = Vec::new();
@@ -432,19 +872,24 @@ pub fn calculate_heat_map(
     if modes.catch {
         layers.push(Layer {
             key: "catch",
-            values: get_catch_layer(num_cells_x, num_cells_y, grid_size, disc, field),
+            values: cache.catch_layer(num_cells_x, num_cells_y, grid_size, game_state, params),
         });
     }
     if modes.difficulty {
         layers.push(Layer {
             key: "difficulty",
-            values: get_difficulty_layer(num_cells_x, num_cells_y, grid_size, disc),
+            values: cache.difficulty_layer(num_cells_x, num_cells_y, grid_size, game_state, params),
         });
     }
     if modes.marking_difficulty {
-        if let Some((vals, tx, ty)) =
-            get_marking_difficulty_layer(num_cells_x, num_cells_y, grid_size, players, disc)
-        {
+        if let Some((vals, tx, ty)) = cache.marking_layer(
+            num_cells_x,
+            num_cells_y,
+            grid_size,
+            players,
+            disc,
+            &params.mark,
+        ) {
             thrower_x = tx;
             thrower_y = ty;
             layers.push(Layer {
@@ -456,7 +901,64 @@ pub fn calculate_heat_map(
     if modes.coverage {
         layers.push(Layer {
             key: "coverage",
-            values: get_coverage_layer(num_cells_x, num_cells_y, grid_size, players, disc),
+            values: Arc::new(get_coverage_layer(
+                num_cells_x,
+                num_cells_y,
+                grid_size,
+                players,
+                disc,
+                &params.coverage,
+            )),
+        });
+    }
+
+    if modes.reset_space {
+        layers.push(Layer {
+            key: "resetSpace",
+            values: Arc::new(get_reset_space_layer(
+                num_cells_x,
+                num_cells_y,
+                grid_size,
+                disc,
+                params.catch.max_throwback_yards,
+                &params.reset,
+            )),
+        });
+    }
+    if modes.completion {
+        layers.push(Layer {
+            key: "completion",
+            values: Arc::new(get_completion_layer(
+                num_cells_x,
+                num_cells_y,
+                grid_size,
+                game_state,
+                params,
+            )),
+        });
+    }
+    if modes.epv {
+        layers.push(Layer {
+            key: "epv",
+            values: Arc::new(get_epv_layer(
+                num_cells_x,
+                num_cells_y,
+                grid_size,
+                field,
+                &params.epv,
+            )),
+        });
+    }
+    if modes.expected_gain {
+        layers.push(Layer {
+            key: "expectedGain",
+            values: Arc::new(get_expected_gain_layer(
+                num_cells_x,
+                num_cells_y,
+                grid_size,
+                game_state,
+                params,
+            )),
         });
     }
 
@@ -524,7 +1026,12 @@ pub fn calculate_heat_map(
 /// Sum all cell values of the product-combined map (all 4 layers, no
 /// min-max normalisation).  Lower = better defence; higher = better offence.
 /// Returns `None` when there is no disc holder (marking layer unavailable).
-pub fn combined_heat_map_sum(game_state: &GameState, grid_size: f64) -> Option<f64> {
+pub fn combined_heat_map_sum(
+    game_state: &GameState,
+    grid_size: f64,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+) -> Option<f64> {
     let field = &game_state.field;
     let disc = &game_state.disc;
     let players = &game_state.players;
@@ -532,11 +1039,24 @@ pub fn combined_heat_map_sum(game_state: &GameState, grid_size: f64) -> Option<f
     let num_cells_x = (field.total_length / grid_size).ceil() as usize;
     let num_cells_y = (field.field_width / grid_size).ceil() as usize;
 
-    let catch = get_catch_layer(num_cells_x, num_cells_y, grid_size, disc, field);
-    let diff = get_difficulty_layer(num_cells_x, num_cells_y, grid_size, disc);
-    let (mark, _, _) =
-        get_marking_difficulty_layer(num_cells_x, num_cells_y, grid_size, players, disc)?;
-    let cov = get_coverage_layer(num_cells_x, num_cells_y, grid_size, players, disc);
+    let catch = cache.catch_layer(num_cells_x, num_cells_y, grid_size, game_state, params);
+    let diff = cache.difficulty_layer(num_cells_x, num_cells_y, grid_size, game_state, params);
+    let (mark, _, _) = cache.marking_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        players,
+        disc,
+        &params.mark,
+    )?;
+    let cov = get_coverage_layer(
+        num_cells_x,
+        num_cells_y,
+        grid_size,
+        players,
+        disc,
+        &params.coverage,
+    );
 
     let mut sum = 0.0_f64;
     for x in 0..num_cells_x {
@@ -0,0 +1,218 @@
+//! k-nearest-neighbours defender model.
+//!
+//! Every recorded example is split into one sample per labelled pair: the
+//! features describe the situation (disc and offender position, normalised by
+//! the field size so different field dimensions are comparable) and the
+//! target is where the coach put the defender *relative to its offender*.
+//! Predicting an offset rather than an absolute position lets a handful of
+//! examples generalise across the whole field.
+
+use crate::models::GameState;
+
+// ============================================================================
+// MODEL CONSTANTS
+// ============================================================================
+
+/// Neighbours averaged when the request does not specify `k`.
+pub const KNN_DEFAULT_K: usize = 5;
+
+/// Fewer samples than this and the model refuses to predict.
+pub const KNN_MIN_SAMPLES: usize = 3;
+
+/// Added to neighbour distances before inverting them for weights, so an
+/// exact feature match does not produce an infinite weight.
+const KNN_DISTANCE_EPSILON: f64 = 1e-6;
+
+/// Feature vector: disc x, disc y, offender x, offender y — each in [0, 1].
+pub type Features = [f64; 4];
+
+/// One labelled pair extracted from a recorded example.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefenderSample {
+    pub features: Features,
+    /// Defender position minus offender position (yards).
+    pub offset: (f64, f64),
+}
+
+/// Leave-one-out accuracy of the model over a sample set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaveOneOutError {
+    pub samples: usize,
+    pub mean_yards: f64,
+    pub median_yards: f64,
+    pub max_yards: f64,
+}
+
+/// Features for the offender with `label`, or `None` when there is no such
+/// downfield offender.
+pub fn features_for(gs: &GameState, label: &str) -> Option<Features> {
+    let offender = gs
+        .players
+        .iter()
+        .find(|p| !p.is_defender && !p.has_disc && p.label.as_deref() == Some(label))?;
+    let len = gs.field.total_length;
+    let width = gs.field.field_width;
+    Some([
+        gs.disc.x / len,
+        gs.disc.y / width,
+        offender.x / len,
+        offender.y / width,
+    ])
+}
+
+/// One sample per non-mark defender whose label matches a downfield
+/// offender.
+pub fn samples_from_game_state(gs: &GameState) -> Vec<DefenderSample> {
+    gs.players
+        .iter()
+        .filter(|d| d.is_defender && !d.is_mark)
+        .filter_map(|d| {
+            let label = d.label.as_deref()?;
+            let offender = gs
+                .players
+                .iter()
+                .find(|o| !o.is_defender && !o.has_disc && o.label.as_deref() == Some(label))?;
+            Some(DefenderSample {
+                features: features_for(gs, label)?,
+                offset: (d.x - offender.x, d.y - offender.y),
+            })
+        })
+        .collect()
+}
+
+/// Check a requested `k` against the number of samples it is drawn from.
+pub fn validate_k(k: usize, samples: usize) -> Result<(), String> {
+    if k == 0 || k > samples {
+        return Err(format!(
+            "k must be between 1 and the number of training samples ({samples}), got {k}"
+        ));
+    }
+    Ok(())
+}
+
+/// Inverse-distance-weighted mean offset of the `k` nearest samples.
+/// Returns `None` when there are fewer than `KNN_MIN_SAMPLES` samples.
+pub fn predict_offset(
+    samples: &[DefenderSample],
+    features: &Features,
+    k: usize,
+) -> Option<(f64, f64)> {
+    predict_offset_excluding(samples, None, features, k)
+}
+
+/// `predict_offset` over `samples` without the one at index `skip`.
+fn predict_offset_excluding(
+    samples: &[DefenderSample],
+    skip: Option<usize>,
+    features: &Features,
+    k: usize,
+) -> Option<(f64, f64)> {
+    let available = samples.len() - usize::from(skip.is_some());
+    if available < KNN_MIN_SAMPLES || k == 0 {
+        return None;
+    }
+
+    let mut by_distance: Vec<(f64, &DefenderSample)> = samples
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| Some(i) != skip)
+        .map(|(_, s)| (feature_distance(&s.features, features), s))
+        .collect();
+    by_distance.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut weight_sum = 0.0_f64;
+    let mut dx = 0.0_f64;
+    let mut dy = 0.0_f64;
+    for (dist, sample) in by_distance.iter().take(k) {
+        let w = 1.0 / (dist + KNN_DISTANCE_EPSILON);
+        weight_sum += w;
+        dx += w * sample.offset.0;
+        dy += w * sample.offset.1;
+    }
+    Some((dx / weight_sum, dy / weight_sum))
+}
+
+/// Predict each sample from all the others and report the distance between
+/// the predicted and recorded defender positions.  Returns `None` when there
+/// are too few samples to leave one out.
+pub fn leave_one_out(samples: &[DefenderSample], k: usize) -> Option<LeaveOneOutError> {
+    let mut errors = Vec::with_capacity(samples.len());
+    for (i, sample) in samples.iter().enumerate() {
+        let (px, py) = predict_offset_excluding(samples, Some(i), &sample.features, k)?;
+        let (ex, ey) = (px - sample.offset.0, py - sample.offset.1);
+        errors.push((ex * ex + ey * ey).sqrt());
+    }
+    if errors.is_empty() {
+        return None;
+    }
+
+    errors.sort_by(f64::total_cmp);
+    let n = errors.len();
+    let median_yards = if n % 2 == 1 {
+        errors[n / 2]
+    } else {
+        (errors[n / 2 - 1] + errors[n / 2]) / 2.0
+    };
+    Some(LeaveOneOutError {
+        samples: n,
+        mean_yards: errors.iter().sum::<f64>() / n as f64,
+        median_yards,
+        max_yards: errors[n - 1],
+    })
+}
+
+fn feature_distance(a: &Features, b: &Features) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<DefenderSample> {
+        (0..6)
+            .map(|i| {
+                let t = i as f64 / 6.0;
+                DefenderSample {
+                    features: [t, 0.5, t * t, 1.0 - t],
+                    offset: (i as f64 - 2.0, (i % 3) as f64),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn leave_one_out_predicts_from_the_other_samples() {
+        let samples = samples();
+        let loo = leave_one_out(&samples, 2).unwrap();
+
+        let mut errors: Vec<f64> = (0..samples.len())
+            .map(|i| {
+                let mut rest = samples.clone();
+                let sample = rest.remove(i);
+                let (px, py) = predict_offset(&rest, &sample.features, 2).unwrap();
+                ((px - sample.offset.0).powi(2) + (py - sample.offset.1).powi(2)).sqrt()
+            })
+            .collect();
+        errors.sort_by(f64::total_cmp);
+        assert_eq!(loo.samples, 6);
+        assert_eq!(loo.max_yards, errors[5]);
+        assert!((loo.mean_yards - errors.iter().sum::<f64>() / 6.0).abs() < 1e-12);
+        assert!((loo.median_yards - (errors[2] + errors[3]) / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn too_few_samples_or_zero_k_predict_nothing() {
+        let samples = samples();
+        assert!(predict_offset(&samples[..KNN_MIN_SAMPLES - 1], &[0.0; 4], 1).is_none());
+        assert!(predict_offset(&samples, &[0.0; 4], 0).is_none());
+        assert!(leave_one_out(&samples[..KNN_MIN_SAMPLES], 1).is_none());
+        assert!(validate_k(0, 6).is_err());
+        assert!(validate_k(7, 6).is_err());
+        assert!(validate_k(6, 6).is_ok());
+    }
+}
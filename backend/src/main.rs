@@ -1,42 +1,286 @@
+mod animate;
 mod api;
+mod batch;
+mod cache;
+mod calibrate;
+mod capabilities;
+mod cli;
+mod completion;
+mod config;
+mod epv;
+mod error;
+mod export;
+mod field;
+mod frontend;
 mod game;
 mod heatmap;
+mod knn;
 mod models;
+mod optimize;
+mod recording;
+mod scenarios;
+mod session;
+mod simulate;
+mod telemetry;
+mod tracking;
+mod training;
+mod wind;
 
-use axum::{routing::post, Router};
-use tower_http::cors::{Any, CorsLayer};
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use axum::http::HeaderValue;
+use clap::Parser;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::api::AppState;
+use crate::cli::{Cli, Command};
+use crate::config::Config;
+use crate::frontend::FrontendSource;
+use crate::heatmap::HeatMapParams;
 
 #[tokio::main]
 async fn main() {
-    // Allow any origin so the static HTML file can be opened directly from
-    // the file-system (file://) or a local dev server on any port.
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
+    let cli = Cli::parse();
+    let config = match Config::resolve(cli.config.as_deref(), &cli.server) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    match cli.command {
+        Some(Command::Eval(args)) => {
+            if let Err(err) = cli::run_eval(args, &config) {
+                eprintln!("error: {err}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::ScoreCompletion(args)) => {
+            if let Err(err) = cli::run_score_completion(args, &config) {
+                eprintln!("error: {err}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Calibrate(args)) => {
+            if let Err(err) = cli::run_calibrate(args, &config) {
+                eprintln!("error: {err}");
+                std::process::exit(1);
+            }
+        }
+        None => serve(config, cli.config.as_deref()).await,
+    }
+}
+
+/// Build the CORS layer.  `*` allows any origin so the static HTML file can
+/// be opened directly from the file-system (file://) or a local dev server on
+/// any port; otherwise only the listed origins are accepted.
+fn cors_layer(config: &Config) -> Result<CorsLayer, String> {
+    let origin = if config.allows_any_origin() {
+        AllowOrigin::from(Any)
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|o| HeaderValue::from_str(o).map_err(|_| format!("invalid origin '{o}'")))
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(CorsLayer::new()
+        .allow_origin(origin)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any))
+}
 
-    let app = Router::new()
+/// Every API route.  Keep in step with `capabilities::ENDPOINTS`, which the
+/// capabilities response and the start-up banner list.
+fn api_router(state: AppState) -> Router {
+    Router::new()
+        // Health and capabilities
+        .route("/api/health",       get(api::health_handler))
+        .route("/api/capabilities", get(api::capabilities_handler))
         // Heat-map data
-        .route("/api/heatmap",     post(api::heatmap_handler))
-        .route("/api/heatmap-sum", post(api::heatmap_sum_handler))
+        .route("/api/heatmap",       post(api::heatmap_handler))
+        .route("/api/heatmap-sum",   post(api::heatmap_sum_handler))
+        .route("/api/heatmap-batch", post(api::heatmap_batch_handler))
+        .route("/api/simulate",      post(api::simulate_handler))
+        .route("/api/animate",       post(api::animate_handler))
+        .route("/api/field-presets", get(api::field_presets_handler))
         // Positioning helpers
         .route("/api/position-defender", post(api::position_defender_handler))
         .route("/api/position-offender", post(api::position_offender_handler))
         .route("/api/position-stack",    post(api::position_stack_handler))
+        .route("/api/position-defender-learned", post(api::position_defender_learned_handler))
+        .route("/api/position-defender-hybrid",  post(api::position_defender_hybrid_handler))
+        // Training data for the learned defender
+        .route(
+            "/api/training/examples",
+            get(api::list_examples_handler)
+                .post(api::record_example_handler)
+                .delete(api::clear_examples_handler),
+        )
+        .route("/api/training/examples/:id", delete(api::delete_example_handler))
+        .route("/api/training/evaluate",     get(api::evaluate_training_handler))
+        // Scenario library
+        .route(
+            "/api/scenarios",
+            get(api::list_scenarios_handler).post(api::create_scenario_handler),
+        )
+        .route(
+            "/api/scenarios/:id",
+            get(api::get_scenario_handler)
+                .put(api::update_scenario_handler)
+                .delete(api::delete_scenario_handler),
+        )
+        // Tracking data import
+        .route(
+            "/api/tracking/import",
+            post(api::tracking_import_handler)
+                .layer(DefaultBodyLimit::max(tracking::TRACKING_MAX_BODY_BYTES)),
+        )
+        .route(
+            "/api/tracking/heatmap-sums",
+            post(api::tracking_heatmap_sums_handler)
+                .layer(DefaultBodyLimit::max(tracking::TRACKING_MAX_BODY_BYTES)),
+        )
+        // Recording and replay
+        .route("/api/recordings",             get(api::list_recordings_handler))
+        .route("/api/recordings/start",       post(api::start_recording_handler))
+        .route("/api/recordings/stop",        post(api::stop_recording_handler))
+        .route("/api/recordings/:id",         get(api::get_recording_handler))
+        .route("/api/recordings/:id/replay",  post(api::replay_recording_handler))
+        // Layer cache
+        .route("/api/cache", get(api::cache_stats_handler).delete(api::clear_cache_handler))
+        // Live session (WebSocket)
+        .route("/api/session", get(session::session_handler))
+        // Prometheus scrape target
+        .route("/metrics", get(api::metrics_handler))
+        .with_state(state)
+}
+
+async fn serve(config: Config, config_path: Option<&std::path::Path>) {
+    let cors = match cors_layer(&config) {
+        Ok(cors) => cors,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let addr = config.bind_addr();
+    let origins = config.allowed_origins.join(", ");
+    let grid_size = config.default_grid_size;
+    let custom_model = config.model != HeatMapParams::default();
+    let model_file = config.model_file.clone();
+    let epv_table = config.epv_table.clone();
+    let frontend = config.frontend_source();
+    let data_dir = config.data_dir.clone();
+    let layer_cache_capacity = config.layer_cache_capacity;
+    telemetry::init_logging(config.log_format);
+    let state = AppState::new(config);
+
+    let app = api_router(state);
+    let app = match &frontend {
+        Some(source) => app.merge(frontend::router(source.clone())),
+        None => app,
+    };
+    // Request spans, logs and metrics; gzip / brotli for clients that send
+    // `Accept-Encoding`.
+    let app = app
+        .layer(middleware::from_fn(telemetry::track_requests))
+        .layer(CompressionLayer::new())
         .layer(cors);
 
-    let addr = "0.0.0.0:3000";
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("error: cannot bind {addr}: {err}");
+            std::process::exit(1);
+        }
+    };
+    let local = listener.local_addr().unwrap();
 
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("  Ultimate Frisbee backend  →  http://localhost:3000");
+    println!("  Ultimate Frisbee backend  →  http://localhost:{}", local.port());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("  Listening on   {local}");
+    println!("  CORS origins   {origins}");
+    println!("  Grid size      {grid_size} yd (default)");
+    match (&model_file, custom_model) {
+        (Some(file), _) => println!("  Model params   {}", file.display()),
+        (None, true) => println!("  Model params   custom (from config)"),
+        (None, false) => println!("  Model params   built-in defaults"),
+    }
+    match &epv_table {
+        Some(file) => println!("  EPV table      {}", file.display()),
+        None => println!("  EPV table      (none, built-in ramp)"),
+    }
+    match &frontend {
+        Some(FrontendSource::Dir(dir)) => println!("  Frontend       {} at /", dir.display()),
+        Some(FrontendSource::Embedded) => println!("  Frontend       embedded at /"),
+        None => println!("  Frontend       (not served)"),
+    }
+    match layer_cache_capacity {
+        0 => println!("  Layer cache    (disabled)"),
+        n => println!("  Layer cache    {n} layers"),
+    }
+    println!("  Data dir       {}", data_dir.display());
+    match config_path {
+        Some(path) => println!("  Config file    {}", path.display()),
+        None => println!("  Config file    (none, built-in defaults)"),
+    }
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("  POST /api/heatmap");
-    println!("  POST /api/heatmap-sum");
-    println!("  POST /api/position-defender");
-    println!("  POST /api/position-offender");
-    println!("  POST /api/position-stack");
+    for (methods, path) in capabilities::endpoint_lines() {
+        println!("  {methods:<15} {path}");
+    }
+    if frontend.is_some() {
+        println!("  {:<15} /  (frontend)", "GET");
+    }
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
+    tracing::info!(addr = %local, "listening");
+
     axum::serve(listener, app).await.unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use tower::ServiceExt;
+
+    /// Every endpoint listed in the capabilities is routed with its method.
+    /// Requests carry no body, so handlers answer 4xx at worst; an unrouted
+    /// path hits the test fallback and a wrong method gets 405.
+    #[tokio::test]
+    async fn every_listed_endpoint_is_routed() {
+        let dir = std::env::temp_dir().join(format!("ufb-routes-test-{}", std::process::id()));
+        let config = Config {
+            data_dir: dir.clone(),
+            ..Config::default()
+        };
+        let app = api_router(AppState::new(config))
+            .fallback(|| async { StatusCode::NOT_IMPLEMENTED });
+
+        for endpoint in capabilities::ENDPOINTS {
+            let request = Request::builder()
+                .method(Method::from_bytes(endpoint.method.as_bytes()).unwrap())
+                .uri(endpoint.path.replace(":id", "missing"))
+                .body(Body::empty())
+                .unwrap();
+            let status = app.clone().oneshot(request).await.unwrap().status();
+            assert!(
+                status != StatusCode::NOT_IMPLEMENTED && status != StatusCode::METHOD_NOT_ALLOWED,
+                "{} {} is not routed ({status})",
+                endpoint.method,
+                endpoint.path
+            );
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
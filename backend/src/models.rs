@@ -1,12 +1,20 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::completion::ThrowType;
+use crate::export::ExportFormat;
+use crate::field::{FieldPresetInfo, FieldSpec, LengthUnit};
+use crate::heatmap::HeatMapParams;
+use crate::wind::WindSpec;
 
 // ---------------------------------------------------------------------------
 // Core field / entity types.  All fields use camelCase in JSON so the
 // frontend JavaScript can pass objects without any key transformation.
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+/// Field size in yards.  Requests may also send a preset name or metres; see
+/// `field::FieldSpec`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "FieldSpec")]
 pub struct FieldDimensions {
     pub field_length: f64,   // 70 yards
     pub field_width: f64,    // 40 yards
@@ -19,6 +27,7 @@ pub struct FieldDimensions {
 pub struct Player {
     pub id: String,
     pub team: u32,
+    /// Position in yards, whatever units the field was given in.
     pub x: f64,
     pub y: f64,
     pub color: String,
@@ -44,6 +53,60 @@ pub struct GameState {
     pub players: Vec<Player>,
     pub disc: Disc,
     pub field: FieldDimensions,
+    /// Stall count on the thrower (0–10); 0 when omitted.
+    #[serde(default, deserialize_with = "stall_count")]
+    pub stall: u32,
+    /// Calm when omitted.
+    #[serde(default)]
+    pub wind: Wind,
+}
+
+/// Highest stall count a game state may carry: the thrower is stalled out
+/// at ten.
+pub const MAX_STALL_COUNT: u32 = 10;
+
+/// Deserialize a stall count, rejecting anything past stall-out.
+fn stall_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let stall = u32::deserialize(deserializer)?;
+    if stall > MAX_STALL_COUNT {
+        return Err(serde::de::Error::custom(format!(
+            "stall must be at most {MAX_STALL_COUNT}, got {stall}"
+        )));
+    }
+    Ok(stall)
+}
+
+/// Wind over the field.  `directionDegrees` is where the wind blows
+/// *toward*: 0 = toward the scoring end zone (a downwind attack), 90 =
+/// toward the y = fieldWidth sideline, 180 = into the attack.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "WindSpec")]
+pub struct Wind {
+    pub speed_mph: f64,
+    pub direction_degrees: f64,
+}
+
+impl Wind {
+    pub fn is_calm(&self) -> bool {
+        self.speed_mph == 0.0
+    }
+}
+
+impl GameState {
+    /// Move the player with the given id; the disc follows the player
+    /// holding it.  Returns `false` when there is no such player.
+    pub fn move_player(&mut self, id: &str, x: f64, y: f64) -> bool {
+        let Some(player) = self.players.iter_mut().find(|p| p.id == id) else {
+            return false;
+        };
+        player.x = x;
+        player.y = y;
+        if player.has_disc {
+            self.disc.x = x;
+            self.disc.y = y;
+        }
+        true
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -57,6 +120,20 @@ pub struct HeatMapModes {
     pub difficulty: bool,
     pub marking_difficulty: bool,
     pub coverage: bool,
+    /// Completion probability (see `completion`).  Optional so existing
+    /// clients keep working.
+    #[serde(default)]
+    pub completion: bool,
+    /// Expected possession value (see `epv`).
+    #[serde(default)]
+    pub epv: bool,
+    /// Completion-weighted EPV gain, in [−1, 1] (see `epv`).  Shown on its
+    /// own: the other modes are ignored while it is enabled.
+    #[serde(default)]
+    pub expected_gain: bool,
+    /// Dump / swing space behind the disc for handlers.
+    #[serde(default)]
+    pub reset_space: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,7 +142,9 @@ pub struct HeatMapRequest {
     pub game_state: GameState,
     pub modes: HeatMapModes,
     pub normalize: bool,
-    pub grid_size: f64,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
 }
 
 /// `values[x][y]` — outer index is the x (yard-line) axis, inner is the y
@@ -80,11 +159,21 @@ pub struct HeatMapData {
     pub mode: String,
 }
 
+/// Query string of `POST /api/heatmap`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeatMapQuery {
+    /// `json` (default), `csv`, `npy` or `geojson`.
+    #[serde(default)]
+    pub format: Option<ExportFormat>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HeatMapSumRequest {
     pub game_state: GameState,
-    pub grid_size: f64,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,24 +189,67 @@ pub struct HeatMapSumResponse {
 #[serde(rename_all = "camelCase")]
 pub struct PositionRequest {
     pub game_state: GameState,
-    pub grid_size: f64,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionDefenderRequest {
     pub game_state: GameState,
-    pub grid_size: f64,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
     /// Label of the defender to position (e.g. "1", "2"); that defender is
     /// positioned relative to the offender with the same label.
     pub defender_label: String,
+    /// Where to look; a 5-yard circle on grid-cell centres when omitted.
+    #[serde(default)]
+    pub search: DefenderSearch,
+}
+
+/// Region around the offender searched for the defender's new spot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DefenderSearch {
+    pub shape: SearchShape,
+    /// Radius around the offender (yards); 5 when omitted.
+    pub radius_yards: Option<f64>,
+    /// After the grid pass, refine the best cell with a continuous local
+    /// search, so the result may lie between cell centres.
+    pub refine: bool,
+    /// Only consider spots the defender can reach from where it stands
+    /// within this many seconds, starting from rest; unlimited when omitted.
+    pub reaction_seconds: Option<f64>,
+    /// Top speed (yards per second) for `reactionSeconds`; 7 when omitted.
+    pub max_speed_yps: Option<f64>,
+    /// Acceleration (yards per second²) for `reactionSeconds`; 5 when omitted.
+    pub acceleration_yps2: Option<f64>,
+}
+
+/// Shape of the search region, relative to the offender.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchShape {
+    /// Full circle.
+    #[default]
+    Circle,
+    /// Half of the circle toward the scoring end zone (lower x).
+    GoalSide,
+    /// Half of the circle toward the disc.
+    Under,
+    /// Half of the circle away from the disc.
+    Deep,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionOffenderRequest {
     pub game_state: GameState,
-    pub grid_size: f64,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
     /// Label of the offender to position (e.g. "1", "2"); that offender is
     /// moved to a cell sampled from the combined heat map.
     pub offender_label: String,
@@ -129,3 +261,674 @@ pub struct PositionResponse {
     pub y: f64,
 }
 
+// ---------------------------------------------------------------------------
+// Batch evaluation request / response types
+// ---------------------------------------------------------------------------
+
+/// One entry of a batch request.  `field` and `gridSize` may be omitted when
+/// the request supplies shared values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchGameState {
+    pub players: Vec<Player>,
+    pub disc: Disc,
+    #[serde(default, deserialize_with = "stall_count")]
+    pub stall: u32,
+    #[serde(default)]
+    pub wind: Wind,
+    #[serde(default)]
+    pub field: Option<FieldDimensions>,
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchHeatMapRequest {
+    pub game_states: Vec<BatchGameState>,
+    /// Field used by every entry that does not carry its own.
+    #[serde(default)]
+    pub field: Option<FieldDimensions>,
+    /// Grid size used by every entry that does not carry its own; the
+    /// server's default grid size when neither is given.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+    /// Include the combined heat-map sum for each entry (default `true`).
+    #[serde(default = "default_true")]
+    pub include_sum: bool,
+    /// When present, also return the heat map built from these layers.
+    #[serde(default)]
+    pub modes: Option<HeatMapModes>,
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+/// Result for a single batch entry; `index` is the entry's position in the
+/// request.  When `error` is set the value fields are `null`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchHeatMapResult {
+    pub index: usize,
+    pub sum: Option<f64>,
+    pub heat_map: Option<HeatMapData>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchHeatMapResponse {
+    pub results: Vec<BatchHeatMapResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ---------------------------------------------------------------------------
+// Scenario library types
+// ---------------------------------------------------------------------------
+
+/// Direction the marker forces the thrower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Force {
+    Forehand,
+    Backhand,
+    Middle,
+    Sideline,
+    StraightUp,
+}
+
+/// End zone the offence is attacking, as drawn on the canvas.  The heat-map
+/// model always scores toward low x (`Left`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttackingDirection {
+    Left,
+    Right,
+}
+
+/// Body of `POST /api/scenarios` and `PUT /api/scenarios/:id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioInput {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub force: Option<Force>,
+    #[serde(default)]
+    pub attacking_direction: Option<AttackingDirection>,
+    pub game_state: GameState,
+}
+
+/// A stored scenario.  Timestamps are Unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    pub id: String,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub force: Option<Force>,
+    pub attacking_direction: Option<AttackingDirection>,
+    pub game_state: GameState,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// List entry — a scenario without its game state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioSummary {
+    pub id: String,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub force: Option<Force>,
+    pub attacking_direction: Option<AttackingDirection>,
+    pub updated_at: u64,
+}
+
+/// Query string of `GET /api/scenarios`.  `tag` may be repeated via a
+/// comma-separated list; all listed tags must match.  `q` is a
+/// case-insensitive substring match on name and notes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScenarioQuery {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Training data / learned defender types
+// ---------------------------------------------------------------------------
+
+/// Body of `POST /api/training/examples`: a game state in which the coach
+/// has placed each labelled defender where it should stand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingExampleInput {
+    pub game_state: GameState,
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// A recorded example.  `recordedAt` is Unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingExample {
+    pub id: u64,
+    pub recorded_at: u64,
+    pub notes: String,
+    pub game_state: GameState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordExampleResponse {
+    pub example: TrainingExample,
+    /// Defender / offender pairs extracted from this example.
+    pub samples: usize,
+    /// Pairs across all stored examples.
+    pub total_samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionDefenderLearnedRequest {
+    pub game_state: GameState,
+    pub defender_label: String,
+    /// Neighbours to average; the model default when omitted.
+    #[serde(default)]
+    pub k: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionDefenderHybridRequest {
+    pub game_state: GameState,
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+    pub defender_label: String,
+    #[serde(default)]
+    pub search: DefenderSearch,
+    /// 0 = heat-map optimum only, 1 = closest to the learned prediction;
+    /// 0.5 when omitted.
+    #[serde(default)]
+    pub blend_weight: Option<f64>,
+    /// Neighbours for the learned prediction; the model default when omitted.
+    #[serde(default)]
+    pub k: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrainingEvaluationQuery {
+    #[serde(default)]
+    pub k: Option<usize>,
+}
+
+/// Leave-one-out error of the learned model.  The error fields are `null`
+/// when there are too few samples to evaluate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingEvaluation {
+    pub k: usize,
+    pub examples: usize,
+    pub samples: usize,
+    pub mean_error_yards: Option<f64>,
+    pub median_error_yards: Option<f64>,
+    pub max_error_yards: Option<f64>,
+}
+
+// ---------------------------------------------------------------------------
+// Calibration data
+// ---------------------------------------------------------------------------
+
+/// One recorded throw: the situation, where the disc was thrown and whether
+/// it was caught.  Calibration datasets are JSON Lines of these.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelledThrow {
+    pub game_state: GameState,
+    pub target_x: f64,
+    pub target_y: f64,
+    pub completed: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Point simulation
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationRequest {
+    pub game_state: GameState,
+    /// Number of points to play (default 100).
+    #[serde(default)]
+    pub simulations: Option<usize>,
+    /// Seed for run 0; run `i` uses `seed + i`.  Random when omitted.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Throws after which a point counts as unfinished (default 30).
+    #[serde(default)]
+    pub max_passes: Option<usize>,
+    /// Cell size in yards (default 2, coarser than the heat-map default).
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+}
+
+/// How often a run of opening throws occurred and how many of those points
+/// were scored.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrowSequence {
+    pub throws: Vec<ThrowType>,
+    pub count: usize,
+    pub scores: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResponse {
+    pub simulations: usize,
+    /// Seed actually used, so a random run can be repeated.
+    pub seed: u64,
+    pub scores: usize,
+    pub turnovers: usize,
+    pub unfinished: usize,
+    pub score_rate: f64,
+    pub average_passes: f64,
+    /// Mean throws in scored points; `null` when nothing was scored.
+    pub average_passes_to_score: Option<f64>,
+    /// Most common opening sequences (up to three throws), most frequent
+    /// first.
+    pub sequences: Vec<ThrowSequence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationRequest {
+    pub game_state: GameState,
+    /// Time steps to generate after the initial state.
+    pub ticks: usize,
+    /// Seconds per tick (default 0.2).
+    #[serde(default)]
+    pub tick_seconds: Option<f64>,
+    /// Running speed of every player in yards per second (default 7).
+    #[serde(default)]
+    pub speed_yps: Option<f64>,
+    /// Seed for the cutters' target sampling.  Random when omitted.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Cell size in yards (default 2, as for simulation).
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationFrame {
+    pub tick: usize,
+    pub time_seconds: f64,
+    pub game_state: GameState,
+    /// Combined heat-map sum for this frame; `null` without a thrower.
+    pub sum: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationResponse {
+    /// Seed actually used, so a random run can be repeated.
+    pub seed: u64,
+    pub tick_seconds: f64,
+    /// The initial state (tick 0) followed by one frame per tick.
+    pub frames: Vec<AnimationFrame>,
+}
+
+// ---------------------------------------------------------------------------
+// Recording / replay types
+// ---------------------------------------------------------------------------
+
+/// Body of `POST /api/recordings/start`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartRecordingRequest {
+    #[serde(default)]
+    pub name: String,
+}
+
+/// What a recorded event did.  Tagged by `"kind"` in JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RecordedAction {
+    /// A state evaluated through `POST /api/heatmap-sum`, which the UI calls
+    /// after every move.
+    HeatMapSum,
+    /// A player moved in a WebSocket session.
+    Move { player_id: String, x: f64, y: f64 },
+    /// A `POST /api/position-defender` call and where it put the defender.
+    PositionDefender {
+        defender_label: String,
+        search: DefenderSearch,
+        result: Option<PositionResponse>,
+    },
+    /// A `POST /api/position-offender` call.  `seed` drove the sampling, so
+    /// a replay draws the same random numbers.
+    PositionOffender {
+        offender_label: String,
+        seed: u64,
+        result: Option<PositionResponse>,
+    },
+    /// A `POST /api/position-defender-learned` call.
+    PositionDefenderLearned {
+        defender_label: String,
+        k: usize,
+        result: Option<PositionResponse>,
+    },
+    /// A `POST /api/position-defender-hybrid` call.
+    PositionDefenderHybrid {
+        defender_label: String,
+        search: DefenderSearch,
+        k: usize,
+        blend_weight: f64,
+        result: Option<PositionResponse>,
+    },
+    /// A `POST /api/position-stack` call.
+    PositionStack { result: Option<PositionResponse> },
+}
+
+/// One line of a recording.  `gameState` is the state the action was applied
+/// to and `sum` the combined heat-map sum of the state it produced.
+/// `recordedAt` is Unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedEvent {
+    pub seq: u64,
+    pub recorded_at: u64,
+    pub grid_size: f64,
+    pub action: RecordedAction,
+    pub game_state: GameState,
+    pub sum: Option<f64>,
+}
+
+/// A recording without its events.  `stoppedAt` is `null` while recording,
+/// or when the server exited without stopping it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingSummary {
+    pub id: String,
+    pub name: String,
+    pub started_at: u64,
+    pub stopped_at: Option<u64>,
+    pub active: bool,
+    pub event_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    #[serde(flatten)]
+    pub summary: RecordingSummary,
+    pub events: Vec<RecordedEvent>,
+}
+
+/// One event of `POST /api/recordings/:id/replay`: what was recorded next to
+/// what the current model produces from the same state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedEvent {
+    pub seq: u64,
+    pub action: RecordedAction,
+    /// Positioning result under the current model; `null` for other kinds.
+    pub result: Option<PositionResponse>,
+    pub recorded_sum: Option<f64>,
+    pub sum: Option<f64>,
+    /// `sum − recordedSum`, when both exist.
+    pub delta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResponse {
+    pub id: String,
+    pub events: Vec<ReplayedEvent>,
+    /// Events whose sum or positioning result differs from the recording.
+    pub changed: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Tracking data import types
+// ---------------------------------------------------------------------------
+
+/// Point of the field at the tracking data's `(0, 0)`, seen with the team in
+/// possession attacking left (toward low x).  x runs along the field's
+/// length and y across it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrackingOrigin {
+    /// The server's own frame: back of the scoring end zone, top sideline.
+    #[default]
+    TopLeft,
+    BottomLeft,
+    TopRight,
+    BottomRight,
+    /// Middle of the field, as many tracking systems report it.
+    #[serde(alias = "center")]
+    Centre,
+}
+
+/// How to read a tracking CSV.  See `tracking` for the file layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrackingOptions {
+    /// Field the coordinates are on (default `"usa"`).
+    pub field: FieldDimensions,
+    /// Units of `x` and `y`.
+    pub units: LengthUnit,
+    pub origin: TrackingOrigin,
+    /// Direction the first frame's offence attacks in the data.  Teams keep
+    /// their ends, so frames where the other team has the disc are mirrored.
+    pub attacking_direction: AttackingDirection,
+    /// Team value that is always on offence.  When omitted, the team of each
+    /// frame's disc holder is (the previous frame's when nobody holds it).
+    pub offence_team: Option<String>,
+    /// The defender nearest the disc holder within this distance (yards) is
+    /// the mark.
+    pub mark_distance_yards: f64,
+    pub wind: Wind,
+}
+
+/// Body of `POST /api/tracking/import`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingImportRequest {
+    /// CSV text, header first.
+    pub csv: String,
+    #[serde(flatten)]
+    pub options: TrackingOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingFrame {
+    pub frame: u64,
+    pub game_state: GameState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingImportResponse {
+    /// One game state per frame, in frame order.
+    pub frames: Vec<TrackingFrame>,
+}
+
+/// Body of `POST /api/tracking/heatmap-sums`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingHeatMapRequest {
+    pub csv: String,
+    #[serde(flatten)]
+    pub options: TrackingOptions,
+    /// Cell size in yards; the server's default grid size when omitted.
+    #[serde(default)]
+    pub grid_size: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingFrameSum {
+    pub frame: u64,
+    /// `null` when nobody holds the disc in this frame.
+    pub sum: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingHeatMapResponse {
+    pub frames: Vec<TrackingFrameSum>,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Layer cache statistics
+// ---------------------------------------------------------------------------
+
+/// Response of `GET /api/cache`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerCacheStats {
+    /// Maximum number of cached layers; 0 when caching is disabled.
+    pub capacity: usize,
+    pub entries: usize,
+    pub evictions: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// `hits / (hits + misses)`, 0 before the first lookup.
+    pub hit_rate: f64,
+    pub layers: Vec<LayerCacheStatsEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerCacheStatsEntry {
+    pub layer: String,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// ---------------------------------------------------------------------------
+// Health / capabilities types
+// ---------------------------------------------------------------------------
+
+/// Response of `GET /api/health`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    /// Always `"ok"`; a server that cannot answer does not respond at all.
+    pub status: &'static str,
+    pub version: &'static str,
+    pub uptime_seconds: f64,
+}
+
+/// A layer a client can switch on in `HeatMapModes`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerInfo {
+    /// The `HeatMapModes` key, also `HeatMapData::mode` when shown alone.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Range of the layer's cell values.
+    pub min: f64,
+    pub max: f64,
+    /// Part of the heat-map sum that the positioning helpers minimise.
+    pub in_sum: bool,
+}
+
+/// A way of combining the enabled layers, selected by `normalize`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CombinationModeInfo {
+    pub name: &'static str,
+    pub normalize: bool,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointInfo {
+    pub method: &'static str,
+    pub path: &'static str,
+}
+
+/// Request limits enforced by the server.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    pub min_grid_size: f64,
+    pub max_grid_size: f64,
+    /// Used when a request omits `gridSize`.
+    pub default_grid_size: f64,
+    /// Largest total length or width of an explicit `field`.
+    pub max_field_dimension_yards: f64,
+    pub max_defender_search_radius_yards: f64,
+    pub max_batch_entries: usize,
+    pub max_simulations: usize,
+    pub max_simulation_passes: usize,
+    pub max_animation_ticks: usize,
+    pub max_tracking_body_bytes: usize,
+    pub max_tracking_frames: usize,
+    pub max_stall: u32,
+}
+
+/// Response of `GET /api/capabilities`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilitiesResponse {
+    pub version: &'static str,
+    pub layers: Vec<LayerInfo>,
+    pub combination_modes: Vec<CombinationModeInfo>,
+    pub field_presets: Vec<FieldPresetInfo>,
+    /// `?format=` values of `POST /api/heatmap`.
+    pub export_formats: Vec<ExportFormat>,
+    /// `Accept` media types of the binary heat-map transport.
+    pub binary_encodings: Vec<&'static str>,
+    /// The heat-map parameters every calculation uses, without the EPV
+    /// table (see `epvTableLoaded`).
+    pub default_params: HeatMapParams,
+    pub epv_table_loaded: bool,
+    pub limits: Limits,
+    pub endpoints: Vec<EndpointInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_state(stall: u32) -> serde_json::Result<GameState> {
+        serde_json::from_value(serde_json::json!({
+            "players": [],
+            "disc": { "x": 80.0, "y": 20.0, "holderId": null },
+            "field": "usa",
+            "stall": stall,
+        }))
+    }
+
+    #[test]
+    fn stall_past_stall_out_is_rejected() {
+        assert_eq!(game_state(MAX_STALL_COUNT).unwrap().stall, MAX_STALL_COUNT);
+        let err = game_state(MAX_STALL_COUNT + 1).unwrap_err().to_string();
+        assert!(err.contains("stall must be at most 10"), "{err}");
+    }
+}
@@ -0,0 +1,127 @@
+//! Derivative-free minimisation.
+//!
+//! The heat-map objectives are piecewise (coverage is a step function, the
+//! catch curve has hard cut-offs), so gradients are unavailable or useless;
+//! Nelder–Mead only needs function values.
+
+// ============================================================================
+// NELDER–MEAD CONSTANTS
+// Standard coefficients from the original paper.
+// ============================================================================
+
+const NM_REFLECTION: f64 = 1.0;
+const NM_EXPANSION: f64 = 2.0;
+const NM_CONTRACTION: f64 = 0.5;
+const NM_SHRINK: f64 = 0.5;
+
+/// Result of a minimisation run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimum {
+    pub x: Vec<f64>,
+    pub value: f64,
+    pub iterations: usize,
+}
+
+/// Minimise `f` starting from `x0` with the Nelder–Mead simplex method.
+///
+/// The initial simplex is `x0` plus one vertex per dimension offset by
+/// `step[i]`.  Stops after `max_iterations` or once the spread of function
+/// values across the simplex falls below `tolerance`.
+pub fn nelder_mead<F>(
+    mut f: F,
+    x0: &[f64],
+    step: &[f64],
+    max_iterations: usize,
+    tolerance: f64,
+) -> Minimum
+where
+    F: FnMut(&[f64]) -> f64,
+{
+    let n = x0.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((x0.to_vec(), f(x0)));
+    for i in 0..n {
+        let mut v = x0.to_vec();
+        v[i] += step[i];
+        let fv = f(&v);
+        simplex.push((v, fv));
+    }
+
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[n].1 - simplex[0].1).abs() <= tolerance {
+            break;
+        }
+        iterations += 1;
+
+        // Centroid of every vertex except the worst.
+        let mut centroid = vec![0.0_f64; n];
+        for (v, _) in &simplex[..n] {
+            for (c, vi) in centroid.iter_mut().zip(v) {
+                *c += vi / n as f64;
+            }
+        }
+        let along = |t: f64| -> Vec<f64> {
+            centroid
+                .iter()
+                .zip(&simplex[n].0)
+                .map(|(c, w)| c + t * (c - w))
+                .collect()
+        };
+
+        let reflected = along(NM_REFLECTION);
+        let f_reflected = f(&reflected);
+
+        if f_reflected < simplex[0].1 {
+            let expanded = along(NM_REFLECTION * NM_EXPANSION);
+            let f_expanded = f(&expanded);
+            simplex[n] = if f_expanded < f_reflected {
+                (expanded, f_expanded)
+            } else {
+                (reflected, f_reflected)
+            };
+        } else if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+        } else {
+            let contracted = along(-NM_CONTRACTION);
+            let f_contracted = f(&contracted);
+            if f_contracted < simplex[n].1 {
+                simplex[n] = (contracted, f_contracted);
+            } else {
+                // Shrink every vertex toward the best one.
+                let best = simplex[0].0.clone();
+                for (v, fv) in simplex.iter_mut().skip(1) {
+                    for (vi, bi) in v.iter_mut().zip(&best) {
+                        *vi = bi + NM_SHRINK * (*vi - bi);
+                    }
+                    *fv = f(v);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (x, value) = simplex.swap_remove(0);
+    Minimum {
+        x,
+        value,
+        iterations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_minimum_of_a_quadratic() {
+        let quadratic = |v: &[f64]| (v[0] - 3.0).powi(2) + 2.0 * (v[1] + 1.0).powi(2) + 5.0;
+        let minimum = nelder_mead(quadratic, &[0.0, 0.0], &[1.0, 1.0], 500, 1e-12);
+
+        assert!((minimum.x[0] - 3.0).abs() < 1e-4, "x = {:?}", minimum.x);
+        assert!((minimum.x[1] + 1.0).abs() < 1e-4, "x = {:?}", minimum.x);
+        assert!((minimum.value - 5.0).abs() < 1e-8);
+        assert!(minimum.iterations < 500);
+    }
+}
@@ -0,0 +1,567 @@
+//! Server-side recordings of play, and their replay.
+//!
+//! While a recording is running, every heat-map sum, positioning call and
+//! WebSocket move is appended to `<dir>/<id>.jsonl` as it happens, one JSON
+//! object per line: a `start` line, one `event` line per action and a `stop`
+//! line.  Files are only ever appended to, so a recording cut short by a
+//! crash keeps everything up to its last event.
+//!
+//! Each event carries the full game state it was applied to, so a recording
+//! can be re-run against the current model to see how the sums and
+//! positioning would change after a parameter update.  Offender positioning
+//! is random; its seed is recorded so the replay draws the same numbers.
+//! Learned and hybrid positioning are replayed against the training examples
+//! stored at replay time.
+
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::cache::LayerCache;
+use crate::error::ApiError;
+use crate::game::{
+    position_defender_hybrid, position_defender_learned, position_defender_optimal,
+    position_offender_optimal_with_rng, position_offender_stack,
+};
+use crate::heatmap::{combined_heat_map_sum, HeatMapParams};
+use crate::knn::DefenderSample;
+use crate::models::{
+    GameState, PositionResponse, RecordedAction, RecordedEvent, Recording, RecordingSummary,
+    ReplayResponse, ReplayedEvent,
+};
+use crate::scenarios::now_millis;
+
+const RECORDING_EXTENSION: &str = "jsonl";
+
+/// Relative difference below which a replayed sum counts as unchanged.
+const REPLAY_TOLERANCE: f64 = 1e-9;
+
+/// One line of a recording file.  Tagged by `"type"` in JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum RecordingLine {
+    Start {
+        id: String,
+        name: String,
+        started_at: u64,
+    },
+    Event(Box<RecordedEvent>),
+    Stop {
+        stopped_at: u64,
+    },
+}
+
+#[derive(Debug)]
+struct ActiveRecording {
+    id: String,
+    name: String,
+    started_at: u64,
+    events: usize,
+    file: tokio::fs::File,
+}
+
+impl ActiveRecording {
+    fn summary(&self, stopped_at: Option<u64>) -> RecordingSummary {
+        RecordingSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            started_at: self.started_at,
+            stopped_at,
+            active: stopped_at.is_none(),
+            event_count: self.events,
+        }
+    }
+
+    async fn append(&mut self, line: &RecordingLine) -> Result<(), ApiError> {
+        let mut text =
+            serde_json::to_string(line).map_err(|e| ApiError::internal(e.to_string()))?;
+        text.push('\n');
+        self.file
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write recording: {e}")))?;
+        self.file
+            .flush()
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write recording: {e}")))
+    }
+}
+
+#[derive(Debug)]
+pub struct RecordingStore {
+    dir: PathBuf,
+    /// The running recording, if any.  At most one runs at a time.
+    active: Mutex<Option<ActiveRecording>>,
+}
+
+impl RecordingStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Whether events are currently being recorded.  Callers use this to skip
+    /// the extra work of preparing an event nobody will store.
+    pub async fn is_recording(&self) -> bool {
+        self.active.lock().await.is_some()
+    }
+
+    /// Start a new recording.  Its id is the start time in Unix milliseconds;
+    /// an empty `name` defaults to the id.
+    pub async fn start(&self, name: String) -> Result<RecordingSummary, ApiError> {
+        let mut active = self.active.lock().await;
+        if let Some(running) = active.as_ref() {
+            return Err(ApiError::bad_request(format!(
+                "recording '{}' is already running; stop it first",
+                running.id
+            )));
+        }
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot create recording dir: {e}")))?;
+
+        let started_at = now_millis();
+        let mut id = started_at.to_string();
+        let mut n = 2;
+        while tokio::fs::try_exists(self.path_for(&id)?)
+            .await
+            .unwrap_or(false)
+        {
+            id = format!("{started_at}-{n}");
+            n += 1;
+        }
+        let name = match name.trim() {
+            "" => id.clone(),
+            name => name.to_string(),
+        };
+
+        let file = tokio::fs::OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.path_for(&id)?)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot create recording: {e}")))?;
+        let mut recording = ActiveRecording {
+            id: id.clone(),
+            name: name.clone(),
+            started_at,
+            events: 0,
+            file,
+        };
+        recording
+            .append(&RecordingLine::Start {
+                id,
+                name,
+                started_at,
+            })
+            .await?;
+        let summary = recording.summary(None);
+        *active = Some(recording);
+        Ok(summary)
+    }
+
+    /// Stop the running recording.
+    pub async fn stop(&self) -> Result<RecordingSummary, ApiError> {
+        let Some(mut recording) = self.active.lock().await.take() else {
+            return Err(ApiError::bad_request("no recording is running"));
+        };
+        let stopped_at = now_millis();
+        recording
+            .append(&RecordingLine::Stop { stopped_at })
+            .await?;
+        Ok(recording.summary(Some(stopped_at)))
+    }
+
+    /// Append an event to the running recording; does nothing when none is
+    /// running.  Write failures are logged rather than returned so that
+    /// recording never fails the call being recorded.
+    pub async fn record(
+        &self,
+        grid_size: f64,
+        action: RecordedAction,
+        game_state: GameState,
+        sum: Option<f64>,
+    ) {
+        let mut active = self.active.lock().await;
+        let Some(recording) = active.as_mut() else {
+            return;
+        };
+        recording.events += 1;
+        let event = RecordedEvent {
+            seq: recording.events as u64,
+            recorded_at: now_millis(),
+            grid_size,
+            action,
+            game_state,
+            sum,
+        };
+        if let Err(e) = recording
+            .append(&RecordingLine::Event(Box::new(event)))
+            .await
+        {
+            tracing::warn!(recording = %recording.id, "cannot record event: {}", e.message);
+        }
+    }
+
+    /// Every recording on disk, newest first.
+    pub async fn list(&self) -> Result<Vec<RecordingSummary>, ApiError> {
+        let active_id = self.active_id().await;
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ApiError::internal(format!("cannot list recordings: {e}"))),
+        };
+        let mut out = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(RECORDING_EXTENSION) {
+                continue;
+            }
+            // Skip unreadable files rather than failing the whole listing.
+            let Ok(text) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Some(recording) = parse(&text, active_id.as_deref()) {
+                out.push(recording.summary);
+            }
+        }
+        out.sort_by(|a, b| {
+            b.started_at
+                .cmp(&a.started_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        Ok(out)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Recording, ApiError> {
+        let path = self.path_for(id)?;
+        let active_id = self.active_id().await;
+        let text = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ApiError::not_found(format!("no recording '{id}'")));
+            }
+            Err(e) => return Err(ApiError::internal(format!("cannot read recording: {e}"))),
+        };
+        parse(&text, active_id.as_deref())
+            .ok_or_else(|| ApiError::internal(format!("corrupt recording '{id}': no start line")))
+    }
+
+    async fn active_id(&self) -> Option<String> {
+        self.active.lock().await.as_ref().map(|r| r.id.clone())
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf, ApiError> {
+        let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(ApiError::not_found(format!("no recording '{id}'")));
+        }
+        Ok(self.dir.join(format!("{id}.{RECORDING_EXTENSION}")))
+    }
+}
+
+/// Parse a recording file.  Lines that fail to parse are skipped with a
+/// warning; `None` when there is no start line.
+fn parse(text: &str, active_id: Option<&str>) -> Option<Recording> {
+    let mut header = None;
+    let mut stopped_at = None;
+    let mut events = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RecordingLine>(line) {
+            Ok(RecordingLine::Start {
+                id,
+                name,
+                started_at,
+            }) => header = Some((id, name, started_at)),
+            Ok(RecordingLine::Event(event)) => events.push(*event),
+            Ok(RecordingLine::Stop { stopped_at: at }) => stopped_at = Some(at),
+            Err(e) => tracing::warn!("skipping recording line {}: {e}", n + 1),
+        }
+    }
+    let (id, name, started_at) = header?;
+    Some(Recording {
+        summary: RecordingSummary {
+            active: active_id == Some(id.as_str()),
+            id,
+            name,
+            started_at,
+            stopped_at,
+            event_count: events.len(),
+        },
+        events,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Replay
+// ---------------------------------------------------------------------------
+
+/// Re-run every event of `recording` against `model` and the training
+/// `samples`.
+pub fn replay(
+    recording: &Recording,
+    model: &HeatMapParams,
+    samples: &[DefenderSample],
+    cache: &LayerCache,
+) -> ReplayResponse {
+    let events: Vec<ReplayedEvent> = recording
+        .events
+        .iter()
+        .map(|event| replay_event(event, model, samples, cache))
+        .collect();
+    let changed = recording
+        .events
+        .iter()
+        .zip(&events)
+        .filter(|(recorded, replayed)| {
+            !sums_match(recorded.sum, replayed.sum)
+                || !results_match(recorded_result(&recorded.action), replayed.result.as_ref())
+        })
+        .count();
+    ReplayResponse {
+        id: recording.summary.id.clone(),
+        events,
+        changed,
+    }
+}
+
+fn replay_event(
+    event: &RecordedEvent,
+    model: &HeatMapParams,
+    samples: &[DefenderSample],
+    cache: &LayerCache,
+) -> ReplayedEvent {
+    let params = model.for_field(&event.game_state.field);
+    let mut gs = event.game_state.clone();
+    let result = match &event.action {
+        RecordedAction::HeatMapSum => None,
+        RecordedAction::Move { player_id, x, y } => {
+            gs.move_player(player_id, *x, *y);
+            None
+        }
+        RecordedAction::PositionDefender {
+            defender_label,
+            search,
+            ..
+        } => position_defender_optimal(
+            &mut gs,
+            event.grid_size,
+            defender_label,
+            &params,
+            cache,
+            search,
+        ),
+        RecordedAction::PositionOffender {
+            offender_label,
+            seed,
+            ..
+        } => position_offender_optimal_with_rng(
+            &mut gs,
+            event.grid_size,
+            offender_label,
+            &params,
+            cache,
+            &mut StdRng::seed_from_u64(*seed),
+        ),
+        RecordedAction::PositionDefenderLearned {
+            defender_label, k, ..
+        } => position_defender_learned(&mut gs, defender_label, samples, *k),
+        RecordedAction::PositionDefenderHybrid {
+            defender_label,
+            search,
+            k,
+            blend_weight,
+            ..
+        } => position_defender_hybrid(
+            &mut gs,
+            event.grid_size,
+            defender_label,
+            &params,
+            cache,
+            search,
+            samples,
+            *k,
+            *blend_weight,
+        ),
+        RecordedAction::PositionStack { .. } => position_offender_stack(&mut gs),
+    };
+    let sum = combined_heat_map_sum(&gs, event.grid_size, &params, cache);
+    ReplayedEvent {
+        seq: event.seq,
+        action: event.action.clone(),
+        result: result.map(|(x, y)| PositionResponse { x, y }),
+        recorded_sum: event.sum,
+        sum,
+        delta: event.sum.zip(sum).map(|(before, after)| after - before),
+    }
+}
+
+fn recorded_result(action: &RecordedAction) -> Option<&PositionResponse> {
+    match action {
+        RecordedAction::HeatMapSum | RecordedAction::Move { .. } => None,
+        RecordedAction::PositionDefender { result, .. }
+        | RecordedAction::PositionOffender { result, .. }
+        | RecordedAction::PositionDefenderLearned { result, .. }
+        | RecordedAction::PositionDefenderHybrid { result, .. }
+        | RecordedAction::PositionStack { result, .. } => result.as_ref(),
+    }
+}
+
+fn sums_match(a: Option<f64>, b: Option<f64>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => close(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn results_match(a: Option<&PositionResponse>, b: Option<&PositionResponse>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => close(a.x, b.x) && close(a.y, b.y),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= REPLAY_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FieldPreset;
+    use crate::models::{DefenderSearch, Disc, Player, Wind};
+
+    const GRID: f64 = 2.0;
+
+    fn player(id: &str, x: f64, y: f64, defender: bool, label: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: id == "m",
+            label: Some(label.to_string()),
+        }
+    }
+
+    fn state() -> GameState {
+        GameState {
+            players: vec![
+                player("t", 70.0, 20.0, false, "0"),
+                player("m", 70.0, 21.0, true, "0"),
+                player("o1", 50.0, 12.0, false, "1"),
+                player("d1", 48.0, 14.0, true, "1"),
+                player("o2", 45.0, 28.0, false, "2"),
+                player("d2", 44.0, 26.0, true, "2"),
+            ],
+            disc: Disc {
+                x: 70.0,
+                y: 20.0,
+                holder_id: Some("t".to_string()),
+            },
+            field: FieldPreset::Usa.dimensions(),
+            stall: 0,
+            wind: Wind::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_with_unchanged_params_reproduces_every_sum() {
+        let dir = std::env::temp_dir().join(format!(
+            "ufb-recording-test-{}-{}",
+            std::process::id(),
+            now_millis()
+        ));
+        let store = RecordingStore::new(dir.clone());
+        let params = HeatMapParams::default();
+        let cache = LayerCache::new(64);
+        let sum = |gs: &GameState| combined_heat_map_sum(gs, GRID, &params, &cache);
+
+        store.start("test".to_string()).await.unwrap();
+
+        // A heat-map sum.
+        let gs = state();
+        store
+            .record(GRID, RecordedAction::HeatMapSum, gs.clone(), sum(&gs))
+            .await;
+
+        // A defender positioning call, recorded with the state before it.
+        let search = DefenderSearch::default();
+        let mut after = gs.clone();
+        let result = position_defender_optimal(&mut after, GRID, "1", &params, &cache, &search)
+            .map(|(x, y)| PositionResponse { x, y });
+        assert!(result.is_some());
+        let action = RecordedAction::PositionDefender {
+            defender_label: "1".to_string(),
+            search,
+            result,
+        };
+        store.record(GRID, action, gs, sum(&after)).await;
+
+        // A seeded offender positioning call.
+        let before = after.clone();
+        let result = position_offender_optimal_with_rng(
+            &mut after,
+            GRID,
+            "2",
+            &params,
+            &cache,
+            &mut StdRng::seed_from_u64(11),
+        )
+        .map(|(x, y)| PositionResponse { x, y });
+        let action = RecordedAction::PositionOffender {
+            offender_label: "2".to_string(),
+            seed: 11,
+            result,
+        };
+        store.record(GRID, action, before, sum(&after)).await;
+
+        // A session move.
+        let before = after.clone();
+        assert!(after.move_player("o1", 40.0, 10.0));
+        let action = RecordedAction::Move {
+            player_id: "o1".to_string(),
+            x: 40.0,
+            y: 10.0,
+        };
+        store.record(GRID, action, before, sum(&after)).await;
+
+        let summary = store.stop().await.unwrap();
+        let recording = store.get(&summary.id).await.unwrap();
+        assert_eq!(recording.events.len(), 4);
+
+        // A cold cache must not change anything either.
+        let replayed = replay(&recording, &params, &[], &LayerCache::disabled());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // Sums are compared as `replay` does: reading the file back may move
+        // a float by its last bit.
+        assert_eq!(replayed.changed, 0);
+        for (recorded, event) in recording.events.iter().zip(&replayed.events) {
+            assert!(recorded.sum.is_some());
+            assert!(sums_match(recorded.sum, event.sum), "event {}", event.seq);
+            assert!(results_match(
+                recorded_result(&recorded.action),
+                event.result.as_ref()
+            ));
+        }
+    }
+}
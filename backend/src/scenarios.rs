@@ -0,0 +1,247 @@
+//! Scenario library stored on disk.
+//!
+//! Each scenario is one pretty-printed JSON file, `<dir>/<id>.json`, so the
+//! library can be inspected, diffed and shared by copying the directory.
+//! Ids are slugs derived from the scenario name and are the only thing ever
+//! joined onto the directory path.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::Mutex;
+
+use crate::error::ApiError;
+use crate::models::{Scenario, ScenarioInput, ScenarioQuery, ScenarioSummary};
+
+#[derive(Debug)]
+pub struct ScenarioStore {
+    dir: PathBuf,
+    /// Serialises writes so id allocation and read-modify-write updates do
+    /// not race.
+    write_lock: Mutex<()>,
+}
+
+impl ScenarioStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// All scenarios matching `query`, sorted by name.
+    pub async fn list(&self, query: &ScenarioQuery) -> Result<Vec<ScenarioSummary>, ApiError> {
+        let tags: Vec<String> = query
+            .tag
+            .as_deref()
+            .map(|t| {
+                t.split(',')
+                    .map(normalise_tag)
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let text = query.q.as_deref().map(str::to_lowercase);
+
+        let mut out = Vec::new();
+        for scenario in self.load_all().await? {
+            if !tags.iter().all(|t| scenario.tags.contains(t)) {
+                continue;
+            }
+            if let Some(text) = &text {
+                let haystack = format!("{}\n{}", scenario.name, scenario.notes).to_lowercase();
+                if !haystack.contains(text.as_str()) {
+                    continue;
+                }
+            }
+            out.push(ScenarioSummary {
+                id: scenario.id,
+                name: scenario.name,
+                tags: scenario.tags,
+                notes: scenario.notes,
+                force: scenario.force,
+                attacking_direction: scenario.attacking_direction,
+                updated_at: scenario.updated_at,
+            });
+        }
+        out.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        Ok(out)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Scenario, ApiError> {
+        let path = self.path_for(id)?;
+        let text = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ApiError::not_found(format!("no scenario '{id}'")));
+            }
+            Err(e) => return Err(ApiError::internal(format!("cannot read scenario: {e}"))),
+        };
+        serde_json::from_str(&text)
+            .map_err(|e| ApiError::internal(format!("corrupt scenario '{id}': {e}")))
+    }
+
+    /// Store a new scenario under a fresh id derived from its name.
+    pub async fn create(&self, input: ScenarioInput) -> Result<Scenario, ApiError> {
+        let input = validate(input)?;
+        let _guard = self.write_lock.lock().await;
+
+        let base = slugify(&input.name);
+        let mut id = base.clone();
+        let mut n = 2;
+        while tokio::fs::try_exists(self.path_for(&id)?)
+            .await
+            .unwrap_or(false)
+        {
+            id = format!("{base}-{n}");
+            n += 1;
+        }
+
+        let now = now_millis();
+        let scenario = Scenario {
+            id,
+            name: input.name,
+            tags: input.tags,
+            notes: input.notes,
+            force: input.force,
+            attacking_direction: input.attacking_direction,
+            game_state: input.game_state,
+            created_at: now,
+            updated_at: now,
+        };
+        self.write(&scenario).await?;
+        Ok(scenario)
+    }
+
+    /// Replace an existing scenario, keeping its id and creation time.
+    pub async fn update(&self, id: &str, input: ScenarioInput) -> Result<Scenario, ApiError> {
+        let input = validate(input)?;
+        let _guard = self.write_lock.lock().await;
+
+        let existing = self.get(id).await?;
+        let scenario = Scenario {
+            id: existing.id,
+            name: input.name,
+            tags: input.tags,
+            notes: input.notes,
+            force: input.force,
+            attacking_direction: input.attacking_direction,
+            game_state: input.game_state,
+            created_at: existing.created_at,
+            updated_at: now_millis(),
+        };
+        self.write(&scenario).await?;
+        Ok(scenario)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), ApiError> {
+        let path = self.path_for(id)?;
+        let _guard = self.write_lock.lock().await;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(ApiError::not_found(format!("no scenario '{id}'")))
+            }
+            Err(e) => Err(ApiError::internal(format!("cannot delete scenario: {e}"))),
+        }
+    }
+
+    async fn load_all(&self) -> Result<Vec<Scenario>, ApiError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ApiError::internal(format!("cannot list scenarios: {e}"))),
+        };
+        let mut out = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            // Skip unreadable files rather than failing the whole listing.
+            let Ok(text) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Ok(scenario) = serde_json::from_str::<Scenario>(&text) {
+                out.push(scenario);
+            }
+        }
+        Ok(out)
+    }
+
+    async fn write(&self, scenario: &Scenario) -> Result<(), ApiError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot create scenario dir: {e}")))?;
+        let text = serde_json::to_string_pretty(scenario)
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        // Write to a temporary file first so a crash never leaves a
+        // half-written scenario behind.
+        let path = self.path_for(&scenario.id)?;
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, text)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write scenario: {e}")))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write scenario: {e}")))
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf, ApiError> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(ApiError::not_found(format!("no scenario '{id}'")));
+        }
+        Ok(self.dir.join(format!("{id}.json")))
+    }
+}
+
+fn validate(mut input: ScenarioInput) -> Result<ScenarioInput, ApiError> {
+    input.name = input.name.trim().to_string();
+    if input.name.is_empty() {
+        return Err(ApiError::bad_request("scenario name must not be empty"));
+    }
+    input.tags = input
+        .tags
+        .iter()
+        .map(|t| normalise_tag(t))
+        .filter(|t| !t.is_empty())
+        .collect();
+    input.tags.sort();
+    input.tags.dedup();
+    Ok(input)
+}
+
+/// Tags are compared case-insensitively and without surrounding spaces.
+fn normalise_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Lower-case ASCII letters and digits, other runs collapsed to `-`.
+fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "scenario".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Current time as Unix milliseconds (0 if the clock is before the epoch).
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
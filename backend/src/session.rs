@@ -0,0 +1,402 @@
+//! Server-side game session driven over a WebSocket.
+//!
+//! The client sends the full `GameState` once, then small incremental
+//! messages (player moves, mode / grid changes).  Every message bumps the
+//! session's sequence number and publishes a snapshot on a `watch` channel;
+//! the compute task only ever sees the *latest* snapshot, so intermediate
+//! states produced faster than they can be evaluated are silently dropped.
+//! Each finished computation is pushed back as one frame containing both the
+//! heat map and the combined sum.
+//!
+//! While a server recording is running, each evaluated move is recorded with
+//! its sum.  Moves coalesced away are only recorded through the later state
+//! that includes them.
+
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
+
+use crate::api::AppState;
+use crate::cache::LayerCache;
+use crate::config::Config;
+use crate::heatmap::{calculate_heat_map, combined_heat_map_sum, validate_grid_size};
+use crate::models::{GameState, HeatMapData, HeatMapModes, RecordedAction};
+use crate::recording::RecordingStore;
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+/// Messages accepted from the client.  Tagged by `"type"` in JSON.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ClientMessage {
+    /// Replace the whole session state.  Must be sent before any other
+    /// message; later messages are ignored until a state is present.
+    Init {
+        game_state: GameState,
+        modes: HeatMapModes,
+        normalize: bool,
+        /// Defaults to the server's grid size when omitted.
+        #[serde(default)]
+        grid_size: Option<f64>,
+    },
+    /// Move a single player.  The disc follows the player holding it.
+    MovePlayer { id: String, x: f64, y: f64 },
+    /// Change which layers are combined and whether the map is normalised.
+    SetModes {
+        modes: HeatMapModes,
+        normalize: bool,
+    },
+    /// Change the heat-map cell size (yards).
+    SetGridSize { grid_size: f64 },
+}
+
+/// Frame pushed to the client after each completed computation.  `seq` is the
+/// sequence number of the state that was evaluated, so the client can tell
+/// which of its moves the result reflects.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUpdate {
+    pub seq: u64,
+    pub heat_map: Option<HeatMapData>,
+    pub sum: Option<f64>,
+}
+
+/// Frame pushed when a client message cannot be applied.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionError {
+    error: String,
+}
+
+// ---------------------------------------------------------------------------
+// Session state
+// ---------------------------------------------------------------------------
+
+/// Everything needed to produce one `SessionUpdate`.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+    seq: u64,
+    game_state: GameState,
+    modes: HeatMapModes,
+    normalize: bool,
+    grid_size: f64,
+    /// The latest `movePlayer`, for recording.
+    last_move: Option<LastMove>,
+}
+
+#[derive(Debug, Clone)]
+struct LastMove {
+    /// Sequence number of the state the move produced.
+    seq: u64,
+    player_id: String,
+    x: f64,
+    y: f64,
+    /// The state the move was applied to, which is what gets recorded.
+    before: GameState,
+}
+
+impl Snapshot {
+    /// Apply an incremental message.  Returns an error string when the
+    /// message refers to something that does not exist.
+    fn apply(&mut self, msg: ClientMessage) -> Result<(), String> {
+        match msg {
+            ClientMessage::Init {
+                game_state,
+                modes,
+                normalize,
+                grid_size,
+            } => {
+                let grid_size = init_grid_size(grid_size, &self.config)?;
+                self.game_state = game_state;
+                self.modes = modes;
+                self.normalize = normalize;
+                self.grid_size = grid_size;
+                self.last_move = None;
+            }
+            ClientMessage::MovePlayer { id, x, y } => {
+                let before = self.game_state.clone();
+                if !self.game_state.move_player(&id, x, y) {
+                    return Err(format!("unknown player id '{id}'"));
+                }
+                self.last_move = Some(LastMove {
+                    seq: self.seq + 1,
+                    player_id: id,
+                    x,
+                    y,
+                    before,
+                });
+            }
+            ClientMessage::SetModes { modes, normalize } => {
+                self.modes = modes;
+                self.normalize = normalize;
+            }
+            ClientMessage::SetGridSize { grid_size } => {
+                validate_grid_size(grid_size).map_err(|e| format!("gridSize {e}"))?;
+                self.grid_size = grid_size;
+            }
+        }
+        self.seq += 1;
+        Ok(())
+    }
+
+    fn evaluate(&self) -> SessionUpdate {
+        let params = self.config.model.for_field(&self.game_state.field);
+        SessionUpdate {
+            seq: self.seq,
+            heat_map: calculate_heat_map(
+                &self.game_state,
+                &self.modes,
+                self.normalize,
+                self.grid_size,
+                &params,
+                &self.cache,
+            ),
+            sum: combined_heat_map_sum(&self.game_state, self.grid_size, &params, &self.cache),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// WebSocket handler
+// ---------------------------------------------------------------------------
+
+/// `GET /api/session` (WebSocket upgrade)
+pub async fn session_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| {
+        run_session(socket, state.config, state.layer_cache, state.recordings)
+    })
+}
+
+/// Drive one client connection until it closes.
+async fn run_session(
+    mut socket: WebSocket,
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+    recordings: Arc<RecordingStore>,
+) {
+    // Latest snapshot; `None` until the client sends `init`.
+    let (state_tx, state_rx) = watch::channel::<Option<Snapshot>>(None);
+    let (update_tx, mut update_rx) = mpsc::channel::<SessionUpdate>(4);
+
+    let worker = tokio::spawn(compute_loop(state_rx, update_tx, recordings));
+    let mut current: Option<Snapshot> = None;
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                let result = serde_json::from_str::<ClientMessage>(&text)
+                    .map_err(|e| e.to_string())
+                    .and_then(|msg| apply_message(&mut current, &config, &cache, msg));
+                match result {
+                    Ok(()) => {
+                        state_tx.send_replace(current.clone());
+                    }
+                    Err(error) => {
+                        let body = serde_json::to_string(&SessionError { error })
+                            .unwrap_or_default();
+                        if socket.send(Message::Text(body)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            Some(update) = update_rx.recv() => {
+                let body = match serde_json::to_string(&update) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                };
+                if socket.send(Message::Text(body)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    worker.abort();
+}
+
+fn apply_message(
+    current: &mut Option<Snapshot>,
+    config: &Arc<Config>,
+    cache: &Arc<LayerCache>,
+    msg: ClientMessage,
+) -> Result<(), String> {
+    match (current.as_mut(), msg) {
+        (
+            None,
+            ClientMessage::Init {
+                game_state,
+                modes,
+                normalize,
+                grid_size,
+            },
+        ) => {
+            let grid_size = init_grid_size(grid_size, config)?;
+            *current = Some(Snapshot {
+                config: config.clone(),
+                cache: cache.clone(),
+                seq: 1,
+                game_state,
+                modes,
+                normalize,
+                grid_size,
+                last_move: None,
+            });
+            Ok(())
+        }
+        (None, _) => Err("session not initialised; send an `init` message first".to_string()),
+        (Some(snapshot), msg) => snapshot.apply(msg),
+    }
+}
+
+/// The grid size an `init` asks for, or the server default, checked against
+/// the same limits as `setGridSize`.
+fn init_grid_size(grid_size: Option<f64>, config: &Config) -> Result<f64, String> {
+    let grid_size = grid_size.unwrap_or(config.default_grid_size);
+    validate_grid_size(grid_size).map_err(|e| format!("gridSize {e}"))?;
+    Ok(grid_size)
+}
+
+/// Evaluate the newest snapshot whenever it changes.  The watch channel only
+/// retains the last value, so any snapshots published while a computation is
+/// running are coalesced into one.
+async fn compute_loop(
+    mut state_rx: watch::Receiver<Option<Snapshot>>,
+    update_tx: mpsc::Sender<SessionUpdate>,
+    recordings: Arc<RecordingStore>,
+) {
+    // Sequence number of the last move recorded, so re-evaluating the same
+    // state after a mode change does not record the move twice.
+    let mut recorded_seq = 0;
+    while state_rx.changed().await.is_ok() {
+        let Some(snapshot) = state_rx.borrow_and_update().clone() else {
+            continue;
+        };
+        let to_record = match &snapshot.last_move {
+            Some(m) if m.seq > recorded_seq && recordings.is_recording().await => {
+                Some((m.clone(), snapshot.grid_size))
+            }
+            _ => None,
+        };
+        let update = match tokio::task::spawn_blocking(move || snapshot.evaluate()).await {
+            Ok(update) => update,
+            Err(_) => continue,
+        };
+        if let Some((m, grid_size)) = to_record {
+            recorded_seq = m.seq;
+            let action = RecordedAction::Move {
+                player_id: m.player_id,
+                x: m.x,
+                y: m.y,
+            };
+            recordings
+                .record(grid_size, action, m.before, update.sum)
+                .await;
+        }
+        if update_tx.send(update).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FieldPreset;
+    use crate::models::{Disc, Player, Wind};
+
+    fn player(id: &str, x: f64, y: f64, defender: bool, label: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: id == "m",
+            label: Some(label.to_string()),
+        }
+    }
+
+    fn init() -> ClientMessage {
+        ClientMessage::Init {
+            game_state: GameState {
+                players: vec![
+                    player("t", 80.0, 20.0, false, "0"),
+                    player("m", 80.0, 21.0, true, "0"),
+                    player("o1", 60.0, 10.0, false, "1"),
+                    player("d1", 59.0, 11.0, true, "1"),
+                ],
+                disc: Disc {
+                    x: 80.0,
+                    y: 20.0,
+                    holder_id: Some("t".to_string()),
+                },
+                field: FieldPreset::Usa.dimensions(),
+                stall: 0,
+                wind: Wind::default(),
+            },
+            modes: HeatMapModes {
+                catch: false,
+                difficulty: false,
+                marking_difficulty: false,
+                coverage: true,
+                completion: false,
+                epv: false,
+                expected_gain: false,
+                reset_space: false,
+            },
+            normalize: false,
+            grid_size: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn moves_queued_before_the_worker_runs_are_evaluated_once() {
+        let config = Arc::new(Config::default());
+        let cache = Arc::new(LayerCache::disabled());
+        // Never started, so nothing is written here.
+        let recordings = Arc::new(RecordingStore::new(std::env::temp_dir()));
+        let (state_tx, state_rx) = watch::channel::<Option<Snapshot>>(None);
+        let (update_tx, mut update_rx) = mpsc::channel::<SessionUpdate>(4);
+
+        let mut current = None;
+        apply_message(&mut current, &config, &cache, init()).unwrap();
+        state_tx.send_replace(current.clone());
+        for x in [58.0, 57.0, 56.0] {
+            let msg = ClientMessage::MovePlayer { id: "d1".to_string(), x, y: 11.0 };
+            apply_message(&mut current, &config, &cache, msg).unwrap();
+            state_tx.send_replace(current.clone());
+        }
+        let latest = current.expect("session is initialised");
+        assert_eq!(latest.seq, 4);
+
+        let worker = tokio::spawn(compute_loop(state_rx, update_tx, recordings));
+        let update = update_rx.recv().await.expect("one update");
+        assert_eq!(update.seq, latest.seq);
+        assert_eq!(update.sum, latest.evaluate().sum);
+
+        // Closing the channel stops the worker with nothing else pending.
+        drop(state_tx);
+        worker.await.unwrap();
+        assert!(update_rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,400 @@
+//! Monte Carlo point simulation.
+//!
+//! Each run plays a point out from the given state: cutters move with
+//! `position_offender_optimal`, defenders answer with
+//! `position_defender_optimal`, the thrower picks a receiver weighted by the
+//! combined heat map at the receiver's spot, and the completion layer decides
+//! whether the throw is caught.  A run ends with a score, a turnover (a drop,
+//! a block, or nobody open) or after `maxPasses` throws.
+//!
+//! Run `i` draws from its own generator seeded with `seed + i`, so results do
+//! not depend on how the runs are spread over threads and any single run can
+//! be replayed.  At most one run per CPU plays at a time, so a large request
+//! queues behind itself instead of filling the blocking pool, and the runs
+//! share a layer cache of their own rather than the server's.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::sync::Semaphore;
+
+use crate::cache::LayerCache;
+use crate::completion::{classify_throw, completion_probability, ThrowType};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::game::{position_defender_optimal, position_offender_optimal_with_rng};
+use crate::heatmap::{calculate_heat_map, validate_grid_size, HeatMapParams, SIDELINE_X_MIN};
+use crate::models::{
+    DefenderSearch, GameState, HeatMapModes, Player, SimulationRequest, SimulationResponse,
+    ThrowSequence,
+};
+
+// ============================================================================
+// SIMULATION CONSTANTS
+// ============================================================================
+
+/// Runs when the request does not specify `simulations`.
+const SIMULATION_DEFAULT_RUNS: usize = 100;
+
+/// Upper bound on `simulations` per request.  A run of `SIMULATION_MAX_PASSES`
+/// throws repositions every player each throw, so this keeps one request to
+/// the same order of work as a full `heatmap-batch`; the score rate's
+/// standard error is already under 1.6 points at this many runs.
+pub const SIMULATION_MAX_RUNS: usize = 1_000;
+
+/// Throws per run when the request does not specify `maxPasses`.
+const SIMULATION_DEFAULT_MAX_PASSES: usize = 30;
+
+/// Upper bound on `maxPasses`.
+pub const SIMULATION_MAX_PASSES: usize = 200;
+
+/// Grid size (yards) when the request omits `gridSize`.  Coarser than the
+/// server default: every defender move evaluates dozens of full heat maps.
+const SIMULATION_DEFAULT_GRID_SIZE: f64 = 2.0;
+
+/// Throws at the start of each point grouped into a sequence.
+const SIMULATION_SEQUENCE_LENGTH: usize = 3;
+
+/// Most common sequences reported.
+const SIMULATION_TOP_SEQUENCES: usize = 10;
+
+/// Distance (yards) the mark sets up from the new thrower after a catch.
+const SIMULATION_MARK_DISTANCE_YARDS: f64 = 1.0;
+
+/// How a simulated point ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointOutcome {
+    Score,
+    Turnover,
+    Unfinished,
+}
+
+/// One simulated point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointResult {
+    pub outcome: PointOutcome,
+    pub throws: Vec<ThrowType>,
+}
+
+/// Validate `req`, play every run on the blocking pool and aggregate.
+pub async fn run_simulation(
+    req: SimulationRequest,
+    config: Arc<Config>,
+) -> Result<SimulationResponse, ApiError> {
+    let runs = req.simulations.unwrap_or(SIMULATION_DEFAULT_RUNS);
+    if runs == 0 || runs > SIMULATION_MAX_RUNS {
+        return Err(ApiError::bad_request(format!(
+            "simulations must be between 1 and {SIMULATION_MAX_RUNS}"
+        )));
+    }
+    let max_passes = req.max_passes.unwrap_or(SIMULATION_DEFAULT_MAX_PASSES);
+    if max_passes == 0 || max_passes > SIMULATION_MAX_PASSES {
+        return Err(ApiError::bad_request(format!(
+            "maxPasses must be between 1 and {SIMULATION_MAX_PASSES}"
+        )));
+    }
+    let grid_size = req.grid_size.unwrap_or(SIMULATION_DEFAULT_GRID_SIZE);
+    validate_grid_size(grid_size).map_err(|e| ApiError::bad_request(format!("gridSize {e}")))?;
+    if !req.game_state.players.iter().any(|p| p.has_disc) {
+        return Err(ApiError::bad_request("no player has the disc"));
+    }
+    let seed = req.seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+    let params = Arc::new(config.model.for_field(&req.game_state.field).into_owned());
+    let game_state = Arc::new(req.game_state);
+    // The intermediate states are never seen again, so they would only evict
+    // the live UI's layers from the shared cache.
+    let cache = Arc::new(LayerCache::new(config.layer_cache_capacity));
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let permits = Arc::new(Semaphore::new(workers));
+    let mut tasks = Vec::with_capacity(runs);
+    for i in 0..runs as u64 {
+        // The semaphore is never closed, so acquiring only waits.
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        let gs = game_state.as_ref().clone();
+        let params = params.clone();
+        let cache = cache.clone();
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i));
+            simulate_point(gs, grid_size, max_passes, &params, &cache, &mut rng)
+        }));
+    }
+
+    let mut results = Vec::with_capacity(runs);
+    for task in tasks {
+        let result = task
+            .await
+            .map_err(|_| ApiError::internal("simulation panicked"))?;
+        results.push(result);
+    }
+    Ok(summarise(&results, seed))
+}
+
+/// Play one point from `gs` until a score, a turnover or `max_passes`
+/// throws.
+pub fn simulate_point<R: Rng>(
+    mut gs: GameState,
+    grid_size: f64,
+    max_passes: usize,
+    params: &HeatMapParams,
+    cache: &LayerCache,
+    rng: &mut R,
+) -> PointResult {
+    let modes = HeatMapModes {
+        catch: true,
+        difficulty: true,
+        marking_difficulty: true,
+        coverage: true,
+        completion: false,
+        epv: false,
+        expected_gain: false,
+        reset_space: false,
+    };
+    let search = DefenderSearch::default();
+    let mut throws = Vec::new();
+
+    for _ in 0..max_passes {
+        // Cutters move, then defenders respond.
+        for label in labels(&gs, |p| !p.is_defender && !p.has_disc) {
+            position_offender_optimal_with_rng(&mut gs, grid_size, &label, params, cache, rng);
+        }
+        for label in labels(&gs, |p| p.is_defender && !p.is_mark) {
+            position_defender_optimal(&mut gs, grid_size, &label, params, cache, &search);
+        }
+
+        // Pick a receiver weighted by the heat map at their spot.
+        let Some(map) = calculate_heat_map(&gs, &modes, false, grid_size, params, cache) else {
+            return PointResult {
+                outcome: PointOutcome::Turnover,
+                throws,
+            };
+        };
+        let cells_x = map.values.len();
+        let cells_y = map.values.first().map_or(0, Vec::len);
+        let receivers: Vec<(usize, f64)> = gs
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| !p.is_defender && !p.has_disc && p.x >= SIDELINE_X_MIN)
+            .map(|(i, p)| {
+                let xi = ((p.x / grid_size).floor().max(0.0) as usize).min(cells_x - 1);
+                let yi = ((p.y / grid_size).floor().max(0.0) as usize).min(cells_y - 1);
+                (i, map.values[xi][yi])
+            })
+            .collect();
+        let total: f64 = receivers.iter().map(|r| r.1).sum();
+        if total <= 0.0 {
+            // Nobody open: the stall count runs out.
+            return PointResult {
+                outcome: PointOutcome::Turnover,
+                throws,
+            };
+        }
+        let threshold = rng.gen::<f64>() * total;
+        let mut cumul = 0.0_f64;
+        let mut receiver_idx = receivers[receivers.len() - 1].0;
+        for &(i, w) in &receivers {
+            cumul += w;
+            if cumul >= threshold {
+                receiver_idx = i;
+                break;
+            }
+        }
+
+        // Throw it.
+        let (rx, ry) = (gs.players[receiver_idx].x, gs.players[receiver_idx].y);
+        let thrower = gs.players.iter().find(|p| p.has_disc);
+        let (tx, ty) = thrower.map_or((gs.disc.x, gs.disc.y), |p| (p.x, p.y));
+        let distance = ((rx - tx).powi(2) + (ry - ty).powi(2)).sqrt();
+        throws.push(classify_throw(
+            thrower,
+            rx,
+            ry,
+            distance,
+            &params.mark,
+            &params.completion,
+        ));
+        let p = completion_probability(&gs, rx, ry, params);
+        if rng.gen::<f64>() >= p {
+            return PointResult {
+                outcome: PointOutcome::Turnover,
+                throws,
+            };
+        }
+
+        complete_pass(&mut gs, receiver_idx);
+        if rx <= gs.field.end_zone_depth {
+            return PointResult {
+                outcome: PointOutcome::Score,
+                throws,
+            };
+        }
+    }
+
+    PointResult {
+        outcome: PointOutcome::Unfinished,
+        throws,
+    }
+}
+
+/// Hand the disc to `receiver_idx`, bring the mark with it and restart the
+/// count.
+fn complete_pass(gs: &mut GameState, receiver_idx: usize) {
+    gs.stall = 0;
+    for (i, p) in gs.players.iter_mut().enumerate() {
+        p.has_disc = i == receiver_idx;
+    }
+    let receiver = &gs.players[receiver_idx];
+    let (rx, ry) = (receiver.x, receiver.y);
+    gs.disc.x = rx;
+    gs.disc.y = ry;
+    gs.disc.holder_id = Some(receiver.id.clone());
+
+    // Mark on the middle side of the thrower, one step away.
+    let side = if ry < gs.field.field_width / 2.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    if let Some(mark) = gs.players.iter_mut().find(|p| p.is_mark) {
+        mark.x = rx;
+        mark.y = ry + side * SIMULATION_MARK_DISTANCE_YARDS;
+    }
+}
+
+/// Distinct labels of the players matching `filter`, in player order.
+pub fn labels(gs: &GameState, filter: impl Fn(&Player) -> bool) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for p in gs.players.iter().filter(|p| filter(p)) {
+        if let Some(label) = &p.label {
+            if !out.contains(label) {
+                out.push(label.clone());
+            }
+        }
+    }
+    out
+}
+
+fn summarise(results: &[PointResult], seed: u64) -> SimulationResponse {
+    let count = |o: PointOutcome| results.iter().filter(|r| r.outcome == o).count();
+    let scores = count(PointOutcome::Score);
+    let total_passes: usize = results.iter().map(|r| r.throws.len()).sum();
+    let scoring_passes: usize = results
+        .iter()
+        .filter(|r| r.outcome == PointOutcome::Score)
+        .map(|r| r.throws.len())
+        .sum();
+
+    let mut by_sequence: HashMap<Vec<ThrowType>, (usize, usize)> = HashMap::new();
+    for r in results.iter().filter(|r| !r.throws.is_empty()) {
+        let key = r
+            .throws
+            .iter()
+            .take(SIMULATION_SEQUENCE_LENGTH)
+            .copied()
+            .collect();
+        let entry = by_sequence.entry(key).or_default();
+        entry.0 += 1;
+        entry.1 += usize::from(r.outcome == PointOutcome::Score);
+    }
+    let mut sequences: Vec<ThrowSequence> = by_sequence
+        .into_iter()
+        .map(|(throws, (count, scores))| ThrowSequence {
+            throws,
+            count,
+            scores,
+        })
+        .collect();
+    sequences.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.throws.cmp(&b.throws)));
+    sequences.truncate(SIMULATION_TOP_SEQUENCES);
+
+    let n = results.len();
+    SimulationResponse {
+        simulations: n,
+        seed,
+        scores,
+        turnovers: count(PointOutcome::Turnover),
+        unfinished: count(PointOutcome::Unfinished),
+        score_rate: scores as f64 / n as f64,
+        average_passes: total_passes as f64 / n as f64,
+        average_passes_to_score: (scores > 0).then(|| scoring_passes as f64 / scores as f64),
+        sequences,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FieldPreset;
+    use crate::models::{Disc, Wind};
+
+    const GRID: f64 = 4.0;
+
+    fn player(id: &str, x: f64, y: f64, defender: bool, label: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: id == "m",
+            label: Some(label.to_string()),
+        }
+    }
+
+    fn state() -> GameState {
+        GameState {
+            players: vec![
+                player("t", 80.0, 20.0, false, "0"),
+                player("m", 80.0, 21.0, true, "0"),
+                player("o1", 60.0, 10.0, false, "1"),
+                player("d1", 59.0, 11.0, true, "1"),
+                player("o2", 50.0, 30.0, false, "2"),
+                player("d2", 49.0, 29.0, true, "2"),
+            ],
+            disc: Disc {
+                x: 80.0,
+                y: 20.0,
+                holder_id: Some("t".to_string()),
+            },
+            field: FieldPreset::Usa.dimensions(),
+            stall: 0,
+            wind: Wind::default(),
+        }
+    }
+
+    fn runs(seed: u64) -> Vec<PointResult> {
+        let params = HeatMapParams::default();
+        let cache = LayerCache::disabled();
+        (0..4)
+            .map(|i| {
+                let mut rng = StdRng::seed_from_u64(seed + i);
+                simulate_point(state(), GRID, 5, &params, &cache, &mut rng)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_point() {
+        let params = HeatMapParams::default();
+        let cache = LayerCache::disabled();
+        let mut a = StdRng::seed_from_u64(7);
+        let mut b = StdRng::seed_from_u64(7);
+        let first = simulate_point(state(), GRID, 5, &params, &cache, &mut a);
+        let second = simulate_point(state(), GRID, 5, &params, &cache, &mut b);
+        assert_eq!(first, second);
+        assert!(!first.throws.is_empty());
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_summary() {
+        assert_eq!(summarise(&runs(42), 42), summarise(&runs(42), 42));
+    }
+}
@@ -0,0 +1,292 @@
+//! Request logging and Prometheus metrics.
+//!
+//! Every request runs inside a `request` span carrying its method and route
+//! (the matched pattern, e.g. `/api/scenarios/:id`, never the raw path).
+//! Handlers add the grid size and player count they worked on, and one event
+//! is logged when the response is ready, with its status and duration.
+//! `RUST_LOG` filters the output (default `info`); `log_format = "json"`
+//! writes one JSON object per line for log collectors.
+//!
+//! The same middleware counts requests and times them into histograms,
+//! rendered with the layer-cache statistics by `GET /metrics` in the
+//! Prometheus text format (0.0.4).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::cache::LayerCache;
+use crate::models::GameState;
+
+/// Upper bounds (seconds) of the request-duration histogram buckets.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+];
+
+/// Route label for requests that matched no route (404s, probes), so stray
+/// paths cannot grow the label set without bound.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Install the global log subscriber.  Logs go to stderr so `eval` output on
+/// stdout stays clean.
+pub fn init_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
+
+/// Add the game state's size to the current request span.
+pub fn record_state(game_state: &GameState) {
+    Span::current().record("players", game_state.players.len());
+}
+
+/// Add the grid size a request was evaluated at to the current request span.
+pub fn record_grid_size(grid_size: f64) {
+    Span::current().record("grid_size", grid_size);
+}
+
+// ---------------------------------------------------------------------------
+// Middleware
+// ---------------------------------------------------------------------------
+
+/// Wrap a request in its span, then log and count it once answered.
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let span = tracing::info_span!(
+        "request",
+        method = %method,
+        route = %route,
+        grid_size = Empty,
+        players = Empty,
+    );
+
+    let start = Instant::now();
+    let response = next.run(req).instrument(span.clone()).await;
+    let elapsed = start.elapsed().as_secs_f64();
+    let status = response.status().as_u16();
+
+    span.in_scope(|| {
+        tracing::info!(status, duration_ms = elapsed * 1000.0, "request completed");
+    });
+    registry().observe(&method, &route, status, elapsed);
+    response
+}
+
+// ---------------------------------------------------------------------------
+// Registry
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Non-cumulative count per bucket of `DURATION_BUCKETS`, plus `+Inf`.
+    buckets: [u64; DURATION_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let i = DURATION_BUCKETS
+            .iter()
+            .position(|&le| value <= le)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.buckets[i] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Series {
+    /// By (method, route, status).
+    requests: BTreeMap<(String, String, u16), u64>,
+    /// By (method, route).
+    durations: BTreeMap<(String, String), Histogram>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    series: Mutex<Series>,
+}
+
+impl Registry {
+    fn observe(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        *series
+            .requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        series
+            .durations
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(seconds);
+    }
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+// ---------------------------------------------------------------------------
+// Prometheus text format
+// ---------------------------------------------------------------------------
+
+/// Every metric in the Prometheus text exposition format, with the
+/// statistics of `cache`.
+pub fn render_metrics(cache: &LayerCache) -> String {
+    let mut out = String::new();
+    {
+        let series = registry().series.lock().unwrap_or_else(|e| e.into_inner());
+
+        header(
+            &mut out,
+            "ufb_http_requests_total",
+            "counter",
+            "HTTP requests answered, by method, route and status.",
+        );
+        for ((method, route, status), count) in &series.requests {
+            let labels = labels(&[
+                ("method", method),
+                ("route", route),
+                ("status", &status.to_string()),
+            ]);
+            let _ = writeln!(out, "ufb_http_requests_total{{{labels}}} {count}");
+        }
+
+        header(
+            &mut out,
+            "ufb_http_request_duration_seconds",
+            "histogram",
+            "Time to produce a response, by method and route.",
+        );
+        for ((method, route), h) in &series.durations {
+            let labels = labels(&[("method", method), ("route", route)]);
+            let mut cumulative = 0;
+            for (i, count) in h.buckets.iter().enumerate() {
+                cumulative += count;
+                let le = DURATION_BUCKETS
+                    .get(i)
+                    .map_or_else(|| "+Inf".to_string(), |le| le.to_string());
+                let _ = writeln!(
+                    out,
+                    "ufb_http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "ufb_http_request_duration_seconds_sum{{{labels}}} {}",
+                h.sum
+            );
+            let _ = writeln!(
+                out,
+                "ufb_http_request_duration_seconds_count{{{labels}}} {}",
+                h.count
+            );
+        }
+    }
+
+    let cache = cache.stats();
+    header(
+        &mut out,
+        "ufb_layer_cache_hits_total",
+        "counter",
+        "Heat-map layers served from the cache.",
+    );
+    for layer in &cache.layers {
+        let labels = labels(&[("layer", &layer.layer)]);
+        let _ = writeln!(out, "ufb_layer_cache_hits_total{{{labels}}} {}", layer.hits);
+    }
+    header(
+        &mut out,
+        "ufb_layer_cache_misses_total",
+        "counter",
+        "Heat-map layers computed because they were not cached.",
+    );
+    for layer in &cache.layers {
+        let labels = labels(&[("layer", &layer.layer)]);
+        let _ = writeln!(
+            out,
+            "ufb_layer_cache_misses_total{{{labels}}} {}",
+            layer.misses
+        );
+    }
+    for (name, kind, help, value) in [
+        (
+            "ufb_layer_cache_evictions_total",
+            "counter",
+            "Layers dropped to make room for newer ones.",
+            cache.evictions as f64,
+        ),
+        (
+            "ufb_layer_cache_entries",
+            "gauge",
+            "Layers currently cached.",
+            cache.entries as f64,
+        ),
+        (
+            "ufb_layer_cache_capacity",
+            "gauge",
+            "Maximum number of cached layers (0 = disabled).",
+            cache.capacity as f64,
+        ),
+    ] {
+        header(&mut out, name, kind, help);
+        let _ = writeln!(out, "{name} {value}");
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape_label(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,618 @@
+//! Import of per-frame player tracking data.
+//!
+//! The input is CSV whose header row names these columns, in any order
+//! (case, spaces and punctuation are ignored):
+//!
+//!   * `frame` — whole frame number;
+//!   * `player` (or `playerId`, `id`);
+//!   * `team` — any value; at most two distinct teams;
+//!   * `x`, `y` — position along the field's length and across its width;
+//!   * `discHolder` (or `holder`, `hasDisc`), optional — `1` / `true` /
+//!     `yes` on the row of the player holding the disc.
+//!
+//! Fields are split on commas and surrounding quotes removed; quoted commas
+//! are not supported.  Each frame becomes one `GameState` in the server's
+//! frame (yards, offence attacking toward x = 0): coordinates are converted
+//! from `units` and `origin`, and frames in which the offence attacks right
+//! are turned half a turn, so the offence's left stays on its left.
+//!
+//! Within a frame the holder is labelled "0" and the other offenders 1, 2, …
+//! in player-id order.  The defender nearest the holder (within
+//! `markDistanceYards`) becomes the mark, and every other defender takes the
+//! label of its nearest offender, closest pairs first.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
+
+use crate::batch::{evaluate_batch, BATCH_MAX_ENTRIES};
+use crate::cache::LayerCache;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::field::{FieldPreset, LengthUnit};
+use crate::models::{
+    AttackingDirection, BatchGameState, BatchHeatMapRequest, Disc, GameState, Player,
+    TrackingFrame, TrackingFrameSum, TrackingHeatMapRequest, TrackingHeatMapResponse,
+    TrackingImportRequest, TrackingImportResponse, TrackingOptions, TrackingOrigin, Wind,
+};
+
+// ============================================================================
+// TRACKING CONSTANTS
+// ============================================================================
+
+/// Default `markDistanceYards`.
+const TRACKING_MARK_DISTANCE_YARDS: f64 = 3.0;
+
+/// Largest request body the tracking endpoints accept (bytes); a match of
+/// tracking data is far larger than the 2 MB allowed elsewhere.
+pub const TRACKING_MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Most frames `POST /api/tracking/heatmap-sums` evaluates per request.
+/// They are evaluated as one heat-map batch, so the batch cap applies; send
+/// a long recording in pieces or every n-th frame.  Import alone only
+/// parses, so it is bounded by the body size instead.
+pub const TRACKING_MAX_FRAMES: usize = BATCH_MAX_ENTRIES;
+
+/// Colours the web UI uses for the two teams.
+const OFFENCE_COLOR: &str = "#ef4444";
+const DEFENCE_COLOR: &str = "#3b82f6";
+
+impl Default for TrackingOptions {
+    fn default() -> Self {
+        Self {
+            field: FieldPreset::Usa.dimensions(),
+            units: LengthUnit::Yards,
+            origin: TrackingOrigin::TopLeft,
+            attacking_direction: AttackingDirection::Left,
+            offence_team: None,
+            mark_distance_yards: TRACKING_MARK_DISTANCE_YARDS,
+            wind: Wind::default(),
+        }
+    }
+}
+
+/// Parse the CSV on the blocking pool and return one game state per frame.
+pub async fn run_import(req: TrackingImportRequest) -> Result<TrackingImportResponse, ApiError> {
+    let frames = import_blocking(req.csv, req.options).await?;
+    Ok(TrackingImportResponse { frames })
+}
+
+/// Import the CSV and evaluate the combined heat-map sum of every frame.
+/// Frames are evaluated as one batch; a frame that fails is reported in its
+/// own entry.  More than `TRACKING_MAX_FRAMES` frames is a 400.
+pub async fn run_heat_map_sums(
+    req: TrackingHeatMapRequest,
+    config: Arc<Config>,
+    cache: Arc<LayerCache>,
+) -> Result<TrackingHeatMapResponse, ApiError> {
+    let frames = import_blocking(req.csv, req.options).await?;
+    if frames.len() > TRACKING_MAX_FRAMES {
+        return Err(ApiError::bad_request(format!(
+            "at most {TRACKING_MAX_FRAMES} frames can be evaluated per request, got {}",
+            frames.len()
+        )));
+    }
+    let numbers: Vec<u64> = frames.iter().map(|f| f.frame).collect();
+    let batch = BatchHeatMapRequest {
+        game_states: frames
+            .into_iter()
+            .map(|f| BatchGameState {
+                players: f.game_state.players,
+                disc: f.game_state.disc,
+                stall: f.game_state.stall,
+                wind: f.game_state.wind,
+                field: Some(f.game_state.field),
+                grid_size: None,
+            })
+            .collect(),
+        field: None,
+        grid_size: req.grid_size,
+        include_sum: true,
+        modes: None,
+        normalize: false,
+    };
+    let response = evaluate_batch(batch, config, cache).await;
+    Ok(TrackingHeatMapResponse {
+        frames: numbers
+            .into_iter()
+            .zip(response.results)
+            .map(|(frame, result)| TrackingFrameSum {
+                frame,
+                sum: result.sum,
+                error: result.error,
+            })
+            .collect(),
+        succeeded: response.succeeded,
+        failed: response.failed,
+    })
+}
+
+async fn import_blocking(
+    csv: String,
+    options: TrackingOptions,
+) -> Result<Vec<TrackingFrame>, ApiError> {
+    tokio::task::spawn_blocking(move || import_csv(&csv, &options))
+        .await
+        .map_err(|_| ApiError::internal("tracking import panicked"))?
+        .map_err(ApiError::bad_request)
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// One data row, in yards from the top-left corner of the data's frame.
+#[derive(Debug, Clone)]
+struct Row {
+    player: String,
+    team: String,
+    x: f64,
+    y: f64,
+    holder: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Frame,
+    Player,
+    Team,
+    X,
+    Y,
+    Holder,
+}
+
+impl Column {
+    fn from_header(name: &str) -> Option<Self> {
+        let key: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match key.as_str() {
+            "frame" | "frameid" | "frameno" | "framenumber" => Some(Column::Frame),
+            "player" | "playerid" | "id" => Some(Column::Player),
+            "team" | "teamid" => Some(Column::Team),
+            "x" => Some(Column::X),
+            "y" => Some(Column::Y),
+            "discholder" | "holder" | "hasdisc" => Some(Column::Holder),
+            _ => None,
+        }
+    }
+}
+
+/// Convert tracking CSV into one game state per frame, in frame order.
+pub fn import_csv(text: &str, options: &TrackingOptions) -> Result<Vec<TrackingFrame>, String> {
+    if !options.mark_distance_yards.is_finite() || options.mark_distance_yards < 0.0 {
+        return Err(format!(
+            "markDistanceYards must be a non-negative number, got {}",
+            options.mark_distance_yards
+        ));
+    }
+    let frames = parse_rows(text, options)?;
+    build_frames(frames, options)
+}
+
+fn split(line: &str) -> impl Iterator<Item = &str> {
+    line.split(',').map(|f| f.trim().trim_matches('"').trim())
+}
+
+fn parse_rows(text: &str, options: &TrackingOptions) -> Result<BTreeMap<u64, Vec<Row>>, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or("the CSV is empty")?;
+
+    let mut columns: [Option<usize>; 6] = [None; 6];
+    for (i, name) in split(header).enumerate() {
+        if let Some(column) = Column::from_header(name) {
+            columns[column as usize].get_or_insert(i);
+        }
+    }
+    let required = |column: Column, name: &str| {
+        columns[column as usize].ok_or_else(|| format!("header has no `{name}` column"))
+    };
+    let frame_col = required(Column::Frame, "frame")?;
+    let player_col = required(Column::Player, "player")?;
+    let team_col = required(Column::Team, "team")?;
+    let x_col = required(Column::X, "x")?;
+    let y_col = required(Column::Y, "y")?;
+    let holder_col = columns[Column::Holder as usize];
+
+    let mut frames: BTreeMap<u64, Vec<Row>> = BTreeMap::new();
+    for (n, line) in lines {
+        let fields: Vec<&str> = split(line).collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("");
+        let line_no = n + 1;
+
+        let frame = field(frame_col).parse::<u64>().map_err(|_| {
+            format!(
+                "line {line_no}: frame `{}` is not a whole number",
+                field(frame_col)
+            )
+        })?;
+        let player = field(player_col);
+        let team = field(team_col);
+        if player.is_empty() || team.is_empty() {
+            return Err(format!("line {line_no}: player and team must not be empty"));
+        }
+        let coordinate = |i: usize, name: &str| {
+            field(i)
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("line {line_no}: {name} `{}` is not a number", field(i)))
+        };
+        let x = options.units.to_yards(coordinate(x_col, "x")?);
+        let y = options.units.to_yards(coordinate(y_col, "y")?);
+        let holder = match holder_col {
+            Some(i) => parse_flag(field(i)).ok_or_else(|| {
+                format!(
+                    "line {line_no}: disc holder `{}` is not true / false",
+                    field(i)
+                )
+            })?,
+            None => false,
+        };
+
+        let (x, y) = from_origin(options, x, y);
+        frames.entry(frame).or_default().push(Row {
+            player: player.to_string(),
+            team: team.to_string(),
+            x,
+            y,
+            holder,
+        });
+    }
+    if frames.is_empty() {
+        return Err("the CSV has no data rows".to_string());
+    }
+    Ok(frames)
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "t" | "yes" | "y" => Some(true),
+        "" | "0" | "false" | "f" | "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+/// `(x, y)` measured from `options.origin`, measured from the top-left
+/// corner instead.
+fn from_origin(options: &TrackingOptions, x: f64, y: f64) -> (f64, f64) {
+    let length = options.field.total_length;
+    let width = options.field.field_width;
+    match options.origin {
+        TrackingOrigin::TopLeft => (x, y),
+        TrackingOrigin::BottomLeft => (x, width - y),
+        TrackingOrigin::TopRight => (length - x, y),
+        TrackingOrigin::BottomRight => (length - x, width - y),
+        TrackingOrigin::Centre => (x + length / 2.0, y + width / 2.0),
+    }
+}
+
+// ============================================================================
+// Game states
+// ============================================================================
+
+fn build_frames(
+    frames: BTreeMap<u64, Vec<Row>>,
+    options: &TrackingOptions,
+) -> Result<Vec<TrackingFrame>, String> {
+    let teams: BTreeSet<&str> = frames.values().flatten().map(|r| r.team.as_str()).collect();
+    if teams.len() > 2 {
+        let names: Vec<&str> = teams.into_iter().collect();
+        return Err(format!(
+            "expected at most two teams, found {}: {}",
+            names.len(),
+            names.join(", ")
+        ));
+    }
+    let first_offence = match &options.offence_team {
+        Some(team) if teams.contains(team.as_str()) => team.clone(),
+        Some(team) => return Err(format!("offenceTeam `{team}` does not appear in the data")),
+        None => frames
+            .values()
+            .flatten()
+            .find(|r| r.holder)
+            .map(|r| r.team.clone())
+            .ok_or("no row marks a disc holder; set `offenceTeam` or add a discHolder column")?,
+    };
+
+    let length = options.field.total_length;
+    let width = options.field.field_width;
+    let mut offence = first_offence.clone();
+    let mut last_disc: Option<(f64, f64)> = None;
+    let mut out = Vec::with_capacity(frames.len());
+
+    for (frame, mut rows) in frames {
+        let mut seen = HashSet::new();
+        if let Some(dup) = rows.iter().find(|r| !seen.insert(r.player.as_str())) {
+            return Err(format!(
+                "frame {frame}: player `{}` appears more than once",
+                dup.player
+            ));
+        }
+        let holders = rows.iter().filter(|r| r.holder).count();
+        if holders > 1 {
+            return Err(format!("frame {frame}: {holders} players hold the disc"));
+        }
+        rows.sort_by(|a, b| compare_ids(&a.player, &b.player));
+        let holder = rows.iter().find(|r| r.holder);
+        if let Some(holder) = holder {
+            if options.offence_team.is_none() {
+                offence = holder.team.clone();
+            }
+            last_disc = Some((holder.x, holder.y));
+        }
+
+        // Teams keep their ends, so the other team attacks the other way.
+        let turned = offence != first_offence;
+        let attacks_right = (options.attacking_direction == AttackingDirection::Right) != turned;
+        let place = |x: f64, y: f64| {
+            if attacks_right {
+                (length - x, width - y)
+            } else {
+                (x, y)
+            }
+        };
+
+        let mut players: Vec<Player> = rows
+            .iter()
+            .map(|r| {
+                let (x, y) = place(r.x, r.y);
+                let defender = r.team != offence;
+                Player {
+                    id: r.player.clone(),
+                    team: if defender { 2 } else { 1 },
+                    x,
+                    y,
+                    color: if defender {
+                        DEFENCE_COLOR
+                    } else {
+                        OFFENCE_COLOR
+                    }
+                    .to_string(),
+                    has_disc: r.holder,
+                    is_defender: defender,
+                    is_mark: false,
+                    label: None,
+                }
+            })
+            .collect();
+        assign_roles(&mut players, options.mark_distance_yards);
+
+        let disc = match (holder, last_disc) {
+            (Some(holder), _) => {
+                let (x, y) = place(holder.x, holder.y);
+                Disc {
+                    x,
+                    y,
+                    holder_id: Some(holder.player.clone()),
+                }
+            }
+            (None, Some((x, y))) => {
+                let (x, y) = place(x, y);
+                Disc {
+                    x,
+                    y,
+                    holder_id: None,
+                }
+            }
+            (None, None) => Disc {
+                x: length / 2.0,
+                y: width / 2.0,
+                holder_id: None,
+            },
+        };
+
+        // `wind` is given as the first frame's offence sees it.
+        let mut wind = options.wind.clone();
+        if turned {
+            wind.direction_degrees = (wind.direction_degrees + 180.0).rem_euclid(360.0);
+        }
+
+        out.push(TrackingFrame {
+            frame,
+            game_state: GameState {
+                players,
+                disc,
+                field: options.field.clone(),
+                stall: 0,
+                wind,
+            },
+        });
+    }
+    Ok(out)
+}
+
+/// Numeric ids in numeric order, before any other ids in text order.
+fn compare_ids(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Label offenders, pick the mark and pair the other defenders with their
+/// nearest offenders.  `players` is in player-id order.
+fn assign_roles(players: &mut [Player], mark_distance: f64) {
+    let mut next = 1;
+    for p in players.iter_mut().filter(|p| !p.is_defender) {
+        if p.has_disc {
+            p.label = Some("0".to_string());
+        } else {
+            p.label = Some(next.to_string());
+            next += 1;
+        }
+    }
+
+    let distance = |a: &Player, b: &Player| (a.x - b.x).hypot(a.y - b.y);
+    if let Some(holder) = players.iter().position(|p| p.has_disc && !p.is_defender) {
+        let mark = players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_defender)
+            .map(|(i, p)| (i, distance(p, &players[holder])))
+            .filter(|&(_, d)| d <= mark_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        if let Some(mark) = mark {
+            players[mark].is_mark = true;
+            players[mark].label = Some("0".to_string());
+        }
+    }
+
+    let defenders: Vec<usize> = (0..players.len())
+        .filter(|&i| players[i].is_defender && !players[i].is_mark)
+        .collect();
+    let offenders: Vec<usize> = (0..players.len())
+        .filter(|&i| !players[i].is_defender && !players[i].has_disc)
+        .collect();
+    let mut pairs: Vec<(f64, usize, usize)> = defenders
+        .iter()
+        .flat_map(|&d| offenders.iter().map(move |&o| (d, o)))
+        .map(|(d, o)| (distance(&players[d], &players[o]), d, o))
+        .collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut paired = HashSet::new();
+    for (_, d, o) in pairs {
+        if players[d].label.is_none() && paired.insert(o) {
+            players[d].label = players[o].label.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "frame,player,team,x,y,discHolder\n";
+
+    fn player<'a>(frame: &'a TrackingFrame, id: &str) -> &'a Player {
+        frame
+            .game_state
+            .players
+            .iter()
+            .find(|p| p.id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn every_origin_maps_to_the_same_spot() {
+        // The same spot, 30 yards from the back of the left end zone and 10
+        // from the top sideline of the 110 × 40 field, from each origin.
+        let cases = [
+            (TrackingOrigin::TopLeft, (30.0, 10.0)),
+            (TrackingOrigin::BottomLeft, (30.0, 30.0)),
+            (TrackingOrigin::TopRight, (80.0, 10.0)),
+            (TrackingOrigin::BottomRight, (80.0, 30.0)),
+            (TrackingOrigin::Centre, (-25.0, -10.0)),
+        ];
+        for (origin, (x, y)) in cases {
+            let options = TrackingOptions {
+                origin,
+                ..TrackingOptions::default()
+            };
+            let csv = format!("{HEADER}1,1,A,{x},{y},1\n");
+            let frames = import_csv(&csv, &options).unwrap();
+            let holder = player(&frames[0], "1");
+            assert_eq!((holder.x, holder.y), (30.0, 10.0), "{origin:?}");
+            assert_eq!(
+                (frames[0].game_state.disc.x, frames[0].game_state.disc.y),
+                (30.0, 10.0)
+            );
+        }
+    }
+
+    #[test]
+    fn metres_are_converted_before_the_origin() {
+        let options = TrackingOptions {
+            units: LengthUnit::Metres,
+            origin: TrackingOrigin::Centre,
+            ..TrackingOptions::default()
+        };
+        let frames = import_csv(&format!("{HEADER}1,1,A,0,0,1\n"), &options).unwrap();
+        let holder = player(&frames[0], "1");
+        assert_eq!((holder.x, holder.y), (55.0, 20.0));
+    }
+
+    #[test]
+    fn turnover_turns_the_field_and_the_wind_for_the_new_offence() {
+        let options = TrackingOptions {
+            wind: Wind {
+                speed_mph: 10.0,
+                direction_degrees: 90.0,
+            },
+            ..TrackingOptions::default()
+        };
+        let csv = format!("{HEADER}1,1,A,30,10,1\n1,11,B,31,10,0\n2,1,A,30,10,0\n2,11,B,31,10,1\n");
+        let frames = import_csv(&csv, &options).unwrap();
+
+        let first = &frames[0];
+        assert!(!player(first, "1").is_defender && player(first, "1").has_disc);
+        assert!(player(first, "11").is_defender);
+        assert_eq!((player(first, "1").x, player(first, "1").y), (30.0, 10.0));
+        assert_eq!(first.game_state.wind.direction_degrees, 90.0);
+
+        // B now attacks the other way: half a turn about the field's centre.
+        let second = &frames[1];
+        let b = player(second, "11");
+        assert!(!b.is_defender && b.has_disc);
+        assert!(player(second, "1").is_defender);
+        assert_eq!((b.x, b.y), (79.0, 30.0));
+        assert_eq!(
+            (second.game_state.disc.x, second.game_state.disc.y),
+            (79.0, 30.0)
+        );
+        assert_eq!(second.game_state.disc.holder_id.as_deref(), Some("11"));
+        assert_eq!(second.game_state.wind.direction_degrees, 270.0);
+    }
+
+    #[test]
+    fn right_attacking_data_is_turned() {
+        let options = TrackingOptions {
+            attacking_direction: AttackingDirection::Right,
+            ..TrackingOptions::default()
+        };
+        let frames = import_csv(&format!("{HEADER}1,1,A,80,10,1\n"), &options).unwrap();
+        let holder = player(&frames[0], "1");
+        assert_eq!((holder.x, holder.y), (30.0, 30.0));
+    }
+
+    #[test]
+    fn mark_and_labels_are_assigned() {
+        let csv = format!(
+            "{HEADER}\
+             1,1,A,50,20,1\n\
+             1,2,A,30,10,0\n\
+             1,3,A,30,30,0\n\
+             1,11,B,51,20,0\n\
+             1,12,B,29,29,0\n\
+             1,13,B,31,11,0\n"
+        );
+        let frames = import_csv(&csv, &TrackingOptions::default()).unwrap();
+        let frame = &frames[0];
+        let label = |id: &str| player(frame, id).label.clone().unwrap();
+
+        assert_eq!(label("1"), "0");
+        assert_eq!(label("2"), "1");
+        assert_eq!(label("3"), "2");
+        assert!(player(frame, "11").is_mark);
+        assert_eq!(label("11"), "0");
+        assert!(!player(frame, "12").is_mark && !player(frame, "13").is_mark);
+        assert_eq!(label("13"), "1");
+        assert_eq!(label("12"), "2");
+    }
+
+    #[test]
+    fn no_mark_beyond_mark_distance() {
+        let csv = format!("{HEADER}1,1,A,50,20,1\n1,2,A,30,10,0\n1,11,B,55,20,0\n");
+        let frames = import_csv(&csv, &TrackingOptions::default()).unwrap();
+        let defender = player(&frames[0], "11");
+        assert!(!defender.is_mark);
+        assert_eq!(defender.label.as_deref(), Some("1"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Server-side store of recorded training examples.
+//!
+//! Examples are appended to `<dir>/examples.jsonl`, one JSON object per line,
+//! and kept in memory together with the k-NN samples extracted from them so
+//! predictions never touch the disk.  Deleting rewrites the file.
+
+use std::path::PathBuf;
+
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::error::ApiError;
+use crate::knn::{samples_from_game_state, DefenderSample};
+use crate::models::{TrainingExample, TrainingExampleInput};
+use crate::scenarios::now_millis;
+
+const EXAMPLES_FILE: &str = "examples.jsonl";
+
+#[derive(Debug, Default)]
+struct Inner {
+    examples: Vec<TrainingExample>,
+    samples: Vec<DefenderSample>,
+    next_id: u64,
+}
+
+impl Inner {
+    fn rebuild_samples(&mut self) {
+        self.samples = self
+            .examples
+            .iter()
+            .flat_map(|e| samples_from_game_state(&e.game_state))
+            .collect();
+    }
+}
+
+#[derive(Debug)]
+pub struct TrainingStore {
+    dir: PathBuf,
+    inner: RwLock<Inner>,
+}
+
+impl TrainingStore {
+    /// Open the store, loading any examples already on disk.  Lines that fail
+    /// to parse are skipped with a warning rather than aborting startup.
+    pub fn open(dir: PathBuf) -> Self {
+        let mut inner = Inner::default();
+        if let Ok(text) = std::fs::read_to_string(dir.join(EXAMPLES_FILE)) {
+            for (n, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<TrainingExample>(line) {
+                    Ok(example) => inner.examples.push(example),
+                    Err(e) => tracing::warn!("skipping training example line {}: {e}", n + 1),
+                }
+            }
+        }
+        inner.next_id = inner.examples.iter().map(|e| e.id + 1).max().unwrap_or(1);
+        inner.rebuild_samples();
+        Self {
+            dir,
+            inner: RwLock::new(inner),
+        }
+    }
+
+    /// Append an example.  Returns it with the number of samples it added
+    /// and the new sample total; rejects examples without any labelled pair.
+    pub async fn record(
+        &self,
+        input: TrainingExampleInput,
+    ) -> Result<(TrainingExample, usize, usize), ApiError> {
+        let new_samples = samples_from_game_state(&input.game_state);
+        if new_samples.is_empty() {
+            return Err(ApiError::bad_request(
+                "example has no labelled defender / offender pair",
+            ));
+        }
+
+        let mut inner = self.inner.write().await;
+        let example = TrainingExample {
+            id: inner.next_id,
+            recorded_at: now_millis(),
+            notes: input.notes,
+            game_state: input.game_state,
+        };
+
+        let mut line =
+            serde_json::to_string(&example).map_err(|e| ApiError::internal(e.to_string()))?;
+        line.push('\n');
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot create training dir: {e}")))?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(EXAMPLES_FILE))
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot open training data: {e}")))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write training data: {e}")))?;
+
+        let added = new_samples.len();
+        inner.next_id += 1;
+        inner.examples.push(example.clone());
+        inner.samples.extend(new_samples);
+        Ok((example, added, inner.samples.len()))
+    }
+
+    pub async fn examples(&self) -> Vec<TrainingExample> {
+        self.inner.read().await.examples.clone()
+    }
+
+    /// Current k-NN samples and the number of examples they came from.
+    pub async fn samples(&self) -> (Vec<DefenderSample>, usize) {
+        let inner = self.inner.read().await;
+        (inner.samples.clone(), inner.examples.len())
+    }
+
+    /// Remove one example, or all of them when `id` is `None`.
+    pub async fn delete(&self, id: Option<u64>) -> Result<(), ApiError> {
+        let mut inner = self.inner.write().await;
+        let mut examples = inner.examples.clone();
+        match id {
+            Some(id) => {
+                let before = examples.len();
+                examples.retain(|e| e.id != id);
+                if examples.len() == before {
+                    return Err(ApiError::not_found(format!("no training example {id}")));
+                }
+            }
+            None => examples.clear(),
+        }
+        self.rewrite(&examples).await?;
+        inner.examples = examples;
+        inner.rebuild_samples();
+        Ok(())
+    }
+
+    async fn rewrite(&self, examples: &[TrainingExample]) -> Result<(), ApiError> {
+        let mut text = String::new();
+        for example in examples {
+            text.push_str(
+                &serde_json::to_string(example).map_err(|e| ApiError::internal(e.to_string()))?,
+            );
+            text.push('\n');
+        }
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot create training dir: {e}")))?;
+        let path = self.dir.join(EXAMPLES_FILE);
+        let tmp = path.with_extension("jsonl.tmp");
+        tokio::fs::write(&tmp, text)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write training data: {e}")))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| ApiError::internal(format!("cannot write training data: {e}")))
+    }
+}
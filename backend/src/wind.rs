@@ -0,0 +1,400 @@
+//! Wind model.
+//!
+//! Every effect is driven by two components of the wind relative to the
+//! throw, each scaled by `speed / reference_mph`:
+//!
+//!   * **tail** — along the throw: positive downwind, negative upwind;
+//!   * **cross** — across the throw, always ≥ 0.
+//!
+//! Both are exactly 0 in calm air, so every factor below is exactly 1 and the
+//! layers are unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Wind;
+
+// ============================================================================
+// WIND CONSTANTS
+// ============================================================================
+
+/// Wind speed (mph) at which the components reach ±1.
+const WIND_REFERENCE_MPH: f64 = 20.0;
+
+/// Extra difficulty of a throw straight into a reference wind (0.5 = 50 %
+/// harder).
+const WIND_UPWIND_DIFFICULTY: f64 = 0.5;
+
+/// Extra difficulty of a throw straight across a reference wind.
+const WIND_CROSSWIND_DIFFICULTY: f64 = 0.3;
+
+/// Change in the catch value of deep space for a throw straight down (or
+/// into) a reference wind.
+const WIND_DEEP_VALUE: f64 = 0.3;
+
+/// Throws shorter than this (yards) are not "deep" and keep their catch value.
+const WIND_DEEP_START_YARDS: f64 = 25.0;
+
+/// Distance (yards) over which the deep-value effect ramps in past
+/// `DEEP_START_YARDS`.
+const WIND_DEEP_RAMP_YARDS: f64 = 20.0;
+
+/// Change in the disc's ground speed per unit of tail component.
+const WIND_FLIGHT_COUPLING: f64 = 0.25;
+
+/// Change in the thrower's range per unit of tail component.
+const WIND_RANGE_COUPLING: f64 = 0.2;
+
+/// Ground speed and range never drop below this share of their calm value.
+const WIND_MIN_FACTOR: f64 = 0.2;
+
+// ============================================================================
+// Runtime parameters
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct WindParams {
+    pub reference_mph: f64,
+    pub upwind_difficulty: f64,
+    pub crosswind_difficulty: f64,
+    pub deep_value: f64,
+    pub deep_start_yards: f64,
+    pub deep_ramp_yards: f64,
+    pub flight_coupling: f64,
+    pub range_coupling: f64,
+}
+
+impl Default for WindParams {
+    fn default() -> Self {
+        Self {
+            reference_mph: WIND_REFERENCE_MPH,
+            upwind_difficulty: WIND_UPWIND_DIFFICULTY,
+            crosswind_difficulty: WIND_CROSSWIND_DIFFICULTY,
+            deep_value: WIND_DEEP_VALUE,
+            deep_start_yards: WIND_DEEP_START_YARDS,
+            deep_ramp_yards: WIND_DEEP_RAMP_YARDS,
+            flight_coupling: WIND_FLIGHT_COUPLING,
+            range_coupling: WIND_RANGE_COUPLING,
+        }
+    }
+}
+
+// ============================================================================
+// Request parsing
+// ============================================================================
+
+/// What a request may send as `wind`; both fields default to 0.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WindSpec {
+    speed_mph: f64,
+    direction_degrees: f64,
+}
+
+impl TryFrom<WindSpec> for Wind {
+    type Error = String;
+
+    fn try_from(spec: WindSpec) -> Result<Self, String> {
+        if !spec.speed_mph.is_finite() || spec.speed_mph < 0.0 {
+            return Err(format!(
+                "wind speedMph must be a finite, non-negative number, got {}",
+                spec.speed_mph
+            ));
+        }
+        if !spec.direction_degrees.is_finite() {
+            return Err(format!(
+                "wind directionDegrees must be finite, got {}",
+                spec.direction_degrees
+            ));
+        }
+        Ok(Wind {
+            speed_mph: spec.speed_mph,
+            direction_degrees: spec.direction_degrees,
+        })
+    }
+}
+
+// ============================================================================
+// Per-throw factors
+// `(dx, dy)` is the throw vector from the disc to the target, in yards.
+// ============================================================================
+
+/// `(tail, cross)` components of `wind` for a throw along `(dx, dy)`.
+/// `(0, 0)` in calm air or for a zero-length throw.
+pub fn wind_components(wind: &Wind, dx: f64, dy: f64, params: &WindParams) -> (f64, f64) {
+    let length = (dx * dx + dy * dy).sqrt();
+    if wind.is_calm() || length == 0.0 || params.reference_mph <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let strength = wind.speed_mph / params.reference_mph;
+    // Direction 0 blows toward the scoring end zone, i.e. toward −x.
+    let theta = wind.direction_degrees.to_radians();
+    let (wx, wy) = (-theta.cos(), theta.sin());
+    let (ux, uy) = (dx / length, dy / length);
+    let tail = strength * (ux * wx + uy * wy);
+    let cross = strength * (ux * wy - uy * wx).abs();
+    (tail, cross)
+}
+
+/// Multiplier (≥ 1) on throw difficulty: upwind and crosswind throws are
+/// harder, downwind throws are not made easier.
+pub fn difficulty_factor(wind: &Wind, dx: f64, dy: f64, params: &WindParams) -> f64 {
+    let (tail, cross) = wind_components(wind, dx, dy, params);
+    1.0 + params.upwind_difficulty * (-tail).max(0.0) + params.crosswind_difficulty * cross
+}
+
+/// Multiplier on the catch value of deep space: above 1 downwind, below 1
+/// upwind, 1 for throws shorter than `deep_start_yards`.
+pub fn deep_value_factor(wind: &Wind, dx: f64, dy: f64, params: &WindParams) -> f64 {
+    let (tail, _) = wind_components(wind, dx, dy, params);
+    let length = (dx * dx + dy * dy).sqrt();
+    let deep = if params.deep_ramp_yards > 0.0 {
+        ((length - params.deep_start_yards) / params.deep_ramp_yards).clamp(0.0, 1.0)
+    } else if length >= params.deep_start_yards {
+        1.0
+    } else {
+        0.0
+    };
+    (1.0 + params.deep_value * tail * deep).max(0.0)
+}
+
+/// Multiplier on the disc's speed over the ground.
+pub fn ground_speed_factor(wind: &Wind, dx: f64, dy: f64, params: &WindParams) -> f64 {
+    let (tail, _) = wind_components(wind, dx, dy, params);
+    (1.0 + params.flight_coupling * tail).max(WIND_MIN_FACTOR)
+}
+
+/// Multiplier on the thrower's range.
+pub fn range_factor(wind: &Wind, dx: f64, dy: f64, params: &WindParams) -> f64 {
+    let (tail, _) = wind_components(wind, dx, dy, params);
+    (1.0 + params.range_coupling * tail).max(WIND_MIN_FACTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::LayerCache;
+    use crate::completion::get_completion_layer;
+    use crate::epv::get_expected_gain_layer;
+    use crate::heatmap::{
+        combined_heat_map_sum, get_catch_layer_for_state, get_difficulty_layer_for_state,
+        HeatMapParams,
+    };
+    use crate::models::{Disc, FieldDimensions, GameState, Player};
+
+    const GRID: f64 = 1.0;
+    const TOLERANCE: f64 = 1e-9;
+
+    fn player(id: &str, x: f64, y: f64, defender: bool, mark: bool, label: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            team: u32::from(defender),
+            x,
+            y,
+            color: String::new(),
+            has_disc: id == "t",
+            is_defender: defender,
+            is_mark: mark,
+            label: Some(label.to_string()),
+        }
+    }
+
+    fn state(players: Vec<Player>, disc: (f64, f64)) -> GameState {
+        GameState {
+            players,
+            disc: Disc {
+                x: disc.0,
+                y: disc.1,
+                holder_id: Some("t".to_string()),
+            },
+            field: FieldDimensions {
+                field_length: 70.0,
+                field_width: 40.0,
+                end_zone_depth: 20.0,
+                total_length: 110.0,
+            },
+            stall: 0,
+            wind: Wind::default(),
+        }
+    }
+
+    fn three_player_state() -> GameState {
+        state(
+            vec![
+                player("t", 80.0, 15.0, false, false, "1"),
+                player("o", 55.0, 15.0, false, false, "1"),
+                player("d", 55.0, 14.0, true, false, "1"),
+            ],
+            (80.0, 15.0),
+        )
+    }
+
+    fn seven_player_state() -> GameState {
+        let offence = [
+            (60.0, 10.0),
+            (55.0, 30.0),
+            (45.0, 20.0),
+            (70.0, 5.0),
+            (65.0, 35.0),
+            (35.0, 15.0),
+        ];
+        let mut players = vec![
+            player("t", 80.0, 20.0, false, false, "0"),
+            player("m", 79.0, 21.0, true, true, "0"),
+        ];
+        for (i, &(x, y)) in offence.iter().enumerate() {
+            let label = (i + 1).to_string();
+            players.push(player(&format!("o{label}"), x, y, false, false, &label));
+            players.push(player(
+                &format!("d{label}"),
+                x - 1.0,
+                y + 1.0,
+                true,
+                false,
+                &label,
+            ));
+        }
+        state(players, (80.0, 20.0))
+    }
+
+    fn dims(gs: &GameState) -> (usize, usize) {
+        (
+            (gs.field.total_length / GRID).ceil() as usize,
+            (gs.field.field_width / GRID).ceil() as usize,
+        )
+    }
+
+    fn total(values: &[Vec<f64>]) -> f64 {
+        values.iter().map(|row| row.iter().sum::<f64>()).sum()
+    }
+
+    /// Layer sums recorded before the wind model existed.
+    fn assert_matches_baseline(
+        gs: &GameState,
+        catch: Option<f64>,
+        difficulty: f64,
+        completion: f64,
+        gain: f64,
+        sum: f64,
+    ) {
+        let params = HeatMapParams::default();
+        let (nx, ny) = dims(gs);
+        if let Some(catch) = catch {
+            let v = total(&get_catch_layer_for_state(nx, ny, GRID, gs, &params));
+            assert!((v - catch).abs() < TOLERANCE, "catch {v}");
+        }
+        let v = total(&get_difficulty_layer_for_state(nx, ny, GRID, gs, &params));
+        assert!((v - difficulty).abs() < TOLERANCE, "difficulty {v}");
+        let v = total(&get_completion_layer(nx, ny, GRID, gs, &params));
+        assert!((v - completion).abs() < TOLERANCE, "completion {v}");
+        let v = total(&get_expected_gain_layer(nx, ny, GRID, gs, &params));
+        assert!((v - gain).abs() < TOLERANCE, "expected gain {v}");
+        let v = combined_heat_map_sum(gs, GRID, &params, &LayerCache::disabled()).unwrap();
+        assert!((v - sum).abs() < TOLERANCE, "combined {v}");
+    }
+
+    #[test]
+    fn zero_wind_reproduces_three_player_baseline() {
+        let mut gs = three_player_state();
+        for direction in [0.0, 90.0, 217.0] {
+            gs.wind.direction_degrees = direction;
+            assert_matches_baseline(
+                &gs,
+                Some(2325.3821014222513),
+                994.4746681033126,
+                325.2619339811211,
+                -1968.3971205955258,
+                946.388852427786,
+            );
+        }
+    }
+
+    #[test]
+    fn zero_wind_reproduces_seven_player_baseline() {
+        assert_matches_baseline(
+            &seven_player_state(),
+            Some(2325.3821014222513),
+            999.3105088461762,
+            581.2037168962676,
+            -1697.3749117869315,
+            662.4578572245217,
+        );
+    }
+
+    /// With the thrower on the centre line, calm air gives mirror-image catch
+    /// and difficulty layers across it; a light wind toward the
+    /// y = fieldWidth sideline makes throws to that side easier and deep
+    /// space there more valuable than on the other side.  Completion is not
+    /// symmetric in calm air (the force decides which side is the break), so
+    /// each side of the deep cell is compared with its own calm value.
+    #[test]
+    fn light_crosswind_favours_the_downwind_side() {
+        let params = HeatMapParams::default();
+        let mut gs = state(
+            vec![
+                player("t", 80.0, 20.0, false, false, "0"),
+                player("a", 64.5, 30.5, false, false, "1"),
+                player("b", 64.5, 9.5, false, false, "2"),
+                player("c", 45.5, 30.5, false, false, "3"),
+                player("d", 45.5, 9.5, false, false, "4"),
+            ],
+            (80.0, 20.0),
+        );
+        let (nx, ny) = dims(&gs);
+        // `(x, downwind y, upwind y)` of cells whose centres mirror each other
+        // across y = 20, 10.5 yards to each side: a 15-yard throw and a deep
+        // 35-yard one.  A receiver stands on each.
+        let short = (64, 30, 9);
+        let deep = (45, 30, 9);
+        let layers = |gs: &GameState| {
+            (
+                get_catch_layer_for_state(nx, ny, GRID, gs, &params),
+                get_difficulty_layer_for_state(nx, ny, GRID, gs, &params),
+                get_completion_layer(nx, ny, GRID, gs, &params),
+            )
+        };
+
+        let (calm_catch, calm_difficulty, calm_completion) = layers(&gs);
+        for (x, down, up) in [short, deep] {
+            assert!((calm_catch[x][down] - calm_catch[x][up]).abs() < TOLERANCE);
+            assert!((calm_difficulty[x][down] - calm_difficulty[x][up]).abs() < TOLERANCE);
+        }
+
+        gs.wind = Wind {
+            speed_mph: 5.0,
+            direction_degrees: 90.0,
+        };
+        let (catch, difficulty, completion) = layers(&gs);
+        for (x, down, up) in [short, deep] {
+            assert!(difficulty[x][down] < difficulty[x][up]);
+        }
+        // Short throws are well inside range either way; only deep space
+        // changes value.
+        let (x, down, up) = short;
+        assert!((catch[x][down] - catch[x][up]).abs() < TOLERANCE);
+        let (x, down, up) = deep;
+        assert!(catch[x][down] > catch[x][up]);
+        assert!(
+            completion[x][down] / calm_completion[x][down]
+                > completion[x][up] / calm_completion[x][up]
+        );
+    }
+
+    #[test]
+    fn upwind_throw_is_harder_than_downwind() {
+        let params = WindParams::default();
+        // Blowing toward the scoring end zone; the throw goes forward (−x).
+        let wind = Wind {
+            speed_mph: 15.0,
+            direction_degrees: 0.0,
+        };
+        let downwind = difficulty_factor(&wind, -30.0, 0.0, &params);
+        let upwind = difficulty_factor(&wind, 30.0, 0.0, &params);
+        assert_eq!(downwind, 1.0);
+        assert!(upwind > downwind);
+        assert!(
+            deep_value_factor(&wind, -40.0, 0.0, &params)
+                > deep_value_factor(&wind, 40.0, 0.0, &params)
+        );
+    }
+}
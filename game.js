@@ -8,6 +8,8 @@
  *
  * Start the backend before opening the page:
  *   cd backend && cargo run --release
+ * or let it serve the page too:
+ *   cd backend && cargo run --release -- --embedded-frontend
  */
 
 class UltimateGame {
@@ -29,15 +31,27 @@ class UltimateGame {
             difficulty: false,
             markingDifficulty: false,
             coverage: false,
+            completion: false,
+            epv: false,
+            expectedGain: false,
+            resetSpace: false,
         };
         this.heatMapNormalize = true;
+        this.stall = 0; // stall count on the thrower (0–10)
+        this.wind = { speedMph: 0, directionDegrees: 0 }; // calm
 
         // Player selection for click-to-move
         this.selectedPlayer = null;
         this.clickHitRadiusYards = 2;
 
         // ── Backend integration ──────────────────────────────────────────────
-        this.apiBase = 'http://localhost:3000/api';
+        // Same origin when the page is served by the backend itself; a
+        // `?api=<url>` query parameter overrides (e.g. for a separate dev
+        // server), and file:// pages fall back to the default local backend.
+        this.apiBase = new URLSearchParams(location.search).get('api')
+            ?? (location.protocol.startsWith('http')
+                ? `${location.origin}/api`
+                : 'http://localhost:3000/api');
 
         // Cached results returned by the backend
         this._cachedHeatMap = null;      // last HeatMapData from /api/heatmap
@@ -130,6 +144,8 @@ class UltimateGame {
                 endZoneDepth:  this.field.endZoneDepth,
                 totalLength:   this.field.totalLength,
             },
+            stall: this.stall,
+            wind: this.wind,
         };
     }
 
@@ -161,7 +177,13 @@ class UltimateGame {
             const [hmRes, sumRes] = await Promise.all([
                 fetch(`${this.apiBase}/heatmap`, {
                     method: 'POST',
-                    headers: { 'Content-Type': 'application/json' },
+                    headers: {
+                        'Content-Type': 'application/json',
+                        // Compact binary grid; 8-bit is exact enough once normalised.
+                        'Accept': this.heatMapNormalize
+                            ? 'application/vnd.ufb.heatmap.u8, application/json'
+                            : 'application/vnd.ufb.heatmap.f32, application/json',
+                    },
                     body: JSON.stringify({
                         gameState: gs,
                         modes: {
@@ -169,6 +191,10 @@ class UltimateGame {
                             difficulty:        this.heatMapModesEnabled.difficulty,
                             markingDifficulty: this.heatMapModesEnabled.markingDifficulty,
                             coverage:          this.heatMapModesEnabled.coverage,
+                            completion:        this.heatMapModesEnabled.completion,
+                            epv:               this.heatMapModesEnabled.epv,
+                            expectedGain:      this.heatMapModesEnabled.expectedGain,
+                            resetSpace:        this.heatMapModesEnabled.resetSpace,
                         },
                         normalize: this.heatMapNormalize,
                         gridSize:  this.heatMapGridSize,
@@ -186,7 +212,7 @@ class UltimateGame {
             // Discard if a newer version was queued while we were waiting
             if (version !== this._stateVersion) return;
 
-            const [hmData, sumData] = await Promise.all([hmRes.json(), sumRes.json()]);
+            const [hmData, sumData] = await Promise.all([decodeHeatMap(hmRes), sumRes.json()]);
 
             this._cachedHeatMap    = hmData;        // null when no layers enabled
             this._cachedHeatMapSum = sumData.sum;   // null when no thrower
@@ -265,6 +291,14 @@ class UltimateGame {
     setHeatMapModeEnabled(mode, enabled) {
         if (Object.prototype.hasOwnProperty.call(this.heatMapModesEnabled, mode)) {
             this.heatMapModesEnabled[mode] = !!enabled;
+            // Expected gain is signed and never combined with other layers.
+            if (enabled) {
+                for (const other of Object.keys(this.heatMapModesEnabled)) {
+                    if ((mode === 'expectedGain') !== (other === 'expectedGain')) {
+                        this.heatMapModesEnabled[other] = false;
+                    }
+                }
+            }
             this._markStateDirty();
         }
     }
@@ -278,6 +312,12 @@ class UltimateGame {
     getHeatMapNormalize()         { return this.heatMapNormalize; }
     setHeatMapNormalize(enabled)  { this.heatMapNormalize = !!enabled; this._markStateDirty(); }
 
+    getStall()                    { return this.stall; }
+    setStall(count)               { this.stall = count; this._markStateDirty(); }
+
+    getWind()                     { return this.wind; }
+    setWind(wind)                 { this.wind = { ...wind }; this._markStateDirty(); }
+
     // ═══════════════════════════════════════════════════════════════════════
     // Synchronous getters for cached backend results
     // (returned values may lag one debounce cycle behind latest state)
@@ -291,6 +331,29 @@ class UltimateGame {
         return this._cachedHeatMapSum ?? null;
     }
 
+    // ═══════════════════════════════════════════════════════════════════════
+    // Backend capabilities
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /**
+     * Fetch what the backend supports (`/api/capabilities`) and keep the grid
+     * size inside its limits.  Resolves to the capabilities, or null when the
+     * backend is unreachable or predates the endpoint.
+     */
+    async fetchCapabilities() {
+        try {
+            const res = await fetch(`${this.apiBase}/capabilities`);
+            if (!res.ok) return null;
+            const caps = await res.json();
+            const { minGridSize, maxGridSize } = caps.limits;
+            this.heatMapGridSize = Math.min(Math.max(this.heatMapGridSize, minGridSize), maxGridSize);
+            return caps;
+        } catch (err) {
+            console.warn('[backend] capabilities unavailable:', err.message);
+            return null;
+        }
+    }
+
     // ═══════════════════════════════════════════════════════════════════════
     // Async positioning  (delegates to Rust backend)
     // ═══════════════════════════════════════════════════════════════════════
@@ -358,6 +421,38 @@ class UltimateGame {
         }
     }
 
+    /**
+     * Ask the backend for a timeline of `ticks` steps from the current state
+     * and play it back in real time.  Resolves when playback ends.
+     */
+    async playAnimation(ticks) {
+        try {
+            const res = await fetch(`${this.apiBase}/animate`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    gameState: this._toApiGameState(),
+                    ticks,
+                }),
+            });
+            if (!res.ok) throw new Error(`HTTP ${res.status}`);
+            const data = await res.json();
+            for (const frame of data.frames) {
+                for (const p of frame.gameState.players) {
+                    const player = this.players.find(q => q.id === p.id);
+                    if (player) {
+                        player.x = p.x;
+                        player.y = p.y;
+                    }
+                }
+                this._markStateDirty();
+                await new Promise(resolve => setTimeout(resolve, data.tickSeconds * 1000));
+            }
+        } catch (err) {
+            console.warn('[backend] animate failed:', err.message);
+        }
+    }
+
     /**
      * Move both offenders to stack positions relative to the disc:
      * Offender 1: 20 yards left (downfield), 7 yards down.
@@ -435,3 +530,40 @@ class UltimateGame {
         console.log('Game reset');
     }
 }
+
+/**
+ * Read a `/api/heatmap` response: JSON, `204 No Content` (no heat map) or
+ * the binary transport described in `backend/src/export.rs`.  Resolves to
+ * the same `{ gridSize, values, throwerX, throwerY, mode }` shape (or null),
+ * with `values[x]` a Float32Array column.
+ */
+async function decodeHeatMap(res) {
+    if (res.status === 204) return null;
+    const type = res.headers.get('Content-Type') || '';
+    if (!type.startsWith('application/vnd.ufb.heatmap')) return res.json();
+
+    const buffer = await res.arrayBuffer();
+    const view = new DataView(buffer);
+    const magic = String.fromCharCode(...new Uint8Array(buffer, 0, 4));
+    if (magic !== 'UFBH' || view.getUint8(4) !== 1) throw new Error('bad heat-map encoding');
+    const encoding = view.getUint8(5);
+    const modeLength = view.getUint16(6, true);
+    const nx = view.getUint32(8, true);
+    const ny = view.getUint32(12, true);
+    const [gridSize, throwerX, throwerY, min, max] =
+        [16, 20, 24, 28, 32].map(offset => view.getFloat32(offset, true));
+    const mode = new TextDecoder().decode(new Uint8Array(buffer, 36, modeLength));
+    const start = 36 + Math.ceil(modeLength / 4) * 4;
+
+    let flat;
+    if (encoding === 1) {
+        flat = new Float32Array(buffer, start, nx * ny);
+    } else {
+        const q = new Uint8Array(buffer, start, nx * ny);
+        const scale = (max - min) / 255;
+        flat = Float32Array.from(q, v => min + v * scale);
+    }
+    const values = [];
+    for (let x = 0; x < nx; x++) values.push(flat.subarray(x * ny, (x + 1) * ny));
+    return { gridSize, values, throwerX, throwerY, mode };
+}